@@ -1,62 +1,631 @@
-#[derive(Debug, Copy, Clone)]
+use std::str::FromStr;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct RGB {
     pub r: u8,
     pub g: u8,
     pub b: u8,
 }
 
+/// Errors produced when parsing a color from a string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorParseError {
+    Empty,
+    /// Hex colors must have 3, 6 or 8 digits
+    InvalidLength(usize),
+    InvalidHexDigit(char),
+    UnknownName(String),
+}
+
+impl std::fmt::Display for ColorParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorParseError::Empty => write!(f, "empty color"),
+            ColorParseError::InvalidLength(n) => {
+                write!(f, "hex color has {} digits, expected 3, 6 or 8", n)
+            }
+            ColorParseError::InvalidHexDigit(c) => write!(f, "invalid hex digit {:?}", c),
+            ColorParseError::UnknownName(name) => write!(f, "unknown color name {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for ColorParseError {}
+
+/// CSS named colors, sorted by name
+const CSS_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xF0F8FF),
+    ("antiquewhite", 0xFAEBD7),
+    ("aqua", 0x00FFFF),
+    ("aquamarine", 0x7FFFD4),
+    ("azure", 0xF0FFFF),
+    ("beige", 0xF5F5DC),
+    ("bisque", 0xFFE4C4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xFFEBCD),
+    ("blue", 0x0000FF),
+    ("blueviolet", 0x8A2BE2),
+    ("brown", 0xA52A2A),
+    ("burlywood", 0xDEB887),
+    ("cadetblue", 0x5F9EA0),
+    ("chartreuse", 0x7FFF00),
+    ("chocolate", 0xD2691E),
+    ("coral", 0xFF7F50),
+    ("cornflowerblue", 0x6495ED),
+    ("cornsilk", 0xFFF8DC),
+    ("crimson", 0xDC143C),
+    ("cyan", 0x00FFFF),
+    ("darkblue", 0x00008B),
+    ("darkcyan", 0x008B8B),
+    ("darkgoldenrod", 0xB8860B),
+    ("darkgray", 0xA9A9A9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xA9A9A9),
+    ("darkkhaki", 0xBDB76B),
+    ("darkmagenta", 0x8B008B),
+    ("darkolivegreen", 0x556B2F),
+    ("darkorange", 0xFF8C00),
+    ("darkorchid", 0x9932CC),
+    ("darkred", 0x8B0000),
+    ("darksalmon", 0xE9967A),
+    ("darkseagreen", 0x8FBC8F),
+    ("darkslateblue", 0x483D8B),
+    ("darkslategray", 0x2F4F4F),
+    ("darkslategrey", 0x2F4F4F),
+    ("darkturquoise", 0x00CED1),
+    ("darkviolet", 0x9400D3),
+    ("deeppink", 0xFF1493),
+    ("deepskyblue", 0x00BFFF),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1E90FF),
+    ("firebrick", 0xB22222),
+    ("floralwhite", 0xFFFAF0),
+    ("forestgreen", 0x228B22),
+    ("fuchsia", 0xFF00FF),
+    ("gainsboro", 0xDCDCDC),
+    ("ghostwhite", 0xF8F8FF),
+    ("gold", 0xFFD700),
+    ("goldenrod", 0xDAA520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xADFF2F),
+    ("grey", 0x808080),
+    ("honeydew", 0xF0FFF0),
+    ("hotpink", 0xFF69B4),
+    ("indianred", 0xCD5C5C),
+    ("indigo", 0x4B0082),
+    ("ivory", 0xFFFFF0),
+    ("khaki", 0xF0E68C),
+    ("lavender", 0xE6E6FA),
+    ("lavenderblush", 0xFFF0F5),
+    ("lawngreen", 0x7CFC00),
+    ("lemonchiffon", 0xFFFACD),
+    ("lightblue", 0xADD8E6),
+    ("lightcoral", 0xF08080),
+    ("lightcyan", 0xE0FFFF),
+    ("lightgoldenrodyellow", 0xFAFAD2),
+    ("lightgray", 0xD3D3D3),
+    ("lightgreen", 0x90EE90),
+    ("lightgrey", 0xD3D3D3),
+    ("lightpink", 0xFFB6C1),
+    ("lightsalmon", 0xFFA07A),
+    ("lightseagreen", 0x20B2AA),
+    ("lightskyblue", 0x87CEFA),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xB0C4DE),
+    ("lightyellow", 0xFFFFE0),
+    ("lime", 0x00FF00),
+    ("limegreen", 0x32CD32),
+    ("linen", 0xFAF0E6),
+    ("magenta", 0xFF00FF),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66CDAA),
+    ("mediumblue", 0x0000CD),
+    ("mediumorchid", 0xBA55D3),
+    ("mediumpurple", 0x9370DB),
+    ("mediumseagreen", 0x3CB371),
+    ("mediumslateblue", 0x7B68EE),
+    ("mediumspringgreen", 0x00FA9A),
+    ("mediumturquoise", 0x48D1CC),
+    ("mediumvioletred", 0xC71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xF5FFFA),
+    ("mistyrose", 0xFFE4E1),
+    ("moccasin", 0xFFE4B5),
+    ("navajowhite", 0xFFDEAD),
+    ("navy", 0x000080),
+    ("oldlace", 0xFDF5E6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6B8E23),
+    ("orange", 0xFFA500),
+    ("orangered", 0xFF4500),
+    ("orchid", 0xDA70D6),
+    ("palegoldenrod", 0xEEE8AA),
+    ("palegreen", 0x98FB98),
+    ("paleturquoise", 0xAFEEEE),
+    ("palevioletred", 0xDB7093),
+    ("papayawhip", 0xFFEFD5),
+    ("peachpuff", 0xFFDAB9),
+    ("peru", 0xCD853F),
+    ("pink", 0xFFC0CB),
+    ("plum", 0xDDA0DD),
+    ("powderblue", 0xB0E0E6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xFF0000),
+    ("rosybrown", 0xBC8F8F),
+    ("royalblue", 0x4169E1),
+    ("saddlebrown", 0x8B4513),
+    ("salmon", 0xFA8072),
+    ("sandybrown", 0xF4A460),
+    ("seagreen", 0x2E8B57),
+    ("seashell", 0xFFF5EE),
+    ("sienna", 0xA0522D),
+    ("silver", 0xC0C0C0),
+    ("skyblue", 0x87CEEB),
+    ("slateblue", 0x6A5ACD),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xFFFAFA),
+    ("springgreen", 0x00FF7F),
+    ("steelblue", 0x4682B4),
+    ("tan", 0xD2B48C),
+    ("teal", 0x008080),
+    ("thistle", 0xD8BFD8),
+    ("tomato", 0xFF6347),
+    ("turquoise", 0x40E0D0),
+    ("violet", 0xEE82EE),
+    ("wheat", 0xF5DEB3),
+    ("white", 0xFFFFFF),
+    ("whitesmoke", 0xF5F5F5),
+    ("yellow", 0xFFFF00),
+    ("yellowgreen", 0x9ACD32),
+];
+
 impl RGB {
+    /// Color from a hex string with or without a leading `#`. Panics if it is not a valid
+    /// color, use `str::parse` for user provided strings.
     pub fn hex(s: &str) -> RGB {
+        s.parse().expect("invalid hex color")
+    }
+
+    /// Color from the `RGB`, `RRGGBB` or `RRGGBBAA` hex digits, the alpha channel is discarded
+    fn from_hex_digits(digits: &str) -> Result<RGB, ColorParseError> {
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(ColorParseError::InvalidHexDigit(c));
+        }
+        let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+        match digits.len() {
+            3 => {
+                let short = |i: usize| u8::from_str_radix(&digits[i..i + 1], 16).unwrap() * 17;
+                Ok(RGB {
+                    r: short(0),
+                    g: short(1),
+                    b: short(2),
+                })
+            }
+            6 | 8 => Ok(RGB {
+                r: channel(0),
+                g: channel(2),
+                b: channel(4),
+            }),
+            n => Err(ColorParseError::InvalidLength(n)),
+        }
+    }
+
+    /// Color at the fraction `t` of the way from `self` to `other`, blending raw sRGB bytes
+    pub fn lerp(self, other: RGB, t: f64) -> RGB {
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
         RGB {
-            r: u8::from_str_radix(&s[0..2], 16).unwrap(),
-            g: u8::from_str_radix(&s[2..4], 16).unwrap(),
-            b: u8::from_str_radix(&s[4..6], 16).unwrap(),
+            r: channel(self.r, other.r),
+            g: channel(self.g, other.g),
+            b: channel(self.b, other.b),
+        }
+    }
+
+    /// Color at the fraction `t` of the way from `self` to `other`, blending in `interpolation`
+    pub fn mix(self, other: RGB, t: f64, interpolation: Interpolation) -> RGB {
+        let (c1, c2) = (self.to_unit(), other.to_unit());
+        let mixed = match interpolation {
+            Interpolation::Srgb => return self.lerp(other, t),
+            Interpolation::LinearRgb => {
+                let (l1, l2) = (c1.map(srgb_to_linear), c2.map(srgb_to_linear));
+                [0, 1, 2].map(|i| linear_to_srgb(lerp(l1[i], l2[i], t)))
+            }
+            Interpolation::Oklab => {
+                let lab1 = linear_rgb_to_oklab(c1.map(srgb_to_linear));
+                let lab2 = linear_rgb_to_oklab(c2.map(srgb_to_linear));
+                let lab = [0, 1, 2].map(|i| lerp(lab1[i], lab2[i], t));
+                oklab_to_linear_rgb(lab).map(linear_to_srgb)
+            }
+            Interpolation::Oklch => {
+                let [l1, a1, b1] = linear_rgb_to_oklab(c1.map(srgb_to_linear));
+                let [l2, a2, b2] = linear_rgb_to_oklab(c2.map(srgb_to_linear));
+                let (chroma1, chroma2) = (a1.hypot(b1), a2.hypot(b2));
+                let mut h1 = b1.atan2(a1).to_degrees();
+                let mut h2 = b2.atan2(a2).to_degrees();
+                // Achromatic colors have no hue, take the one of the other end
+                if chroma1 < 1e-6 {
+                    h1 = h2;
+                }
+                if chroma2 < 1e-6 {
+                    h2 = h1;
+                }
+                let chroma = lerp(chroma1, chroma2, t);
+                let hue = lerp_hue(h1, h2, t).to_radians();
+                let lab = [lerp(l1, l2, t), chroma * hue.cos(), chroma * hue.sin()];
+                oklab_to_linear_rgb(lab).map(linear_to_srgb)
+            }
+            Interpolation::Hsv => {
+                let [mut h1, s1, v1] = rgb_to_hsv(c1);
+                let [mut h2, s2, v2] = rgb_to_hsv(c2);
+                if s1 == 0. {
+                    h1 = h2;
+                }
+                if s2 == 0. {
+                    h2 = h1;
+                }
+                hsv_to_rgb([lerp_hue(h1, h2, t), lerp(s1, s2, t), lerp(v1, v2, t)])
+            }
+        };
+        RGB::from_unit(mixed)
+    }
+
+    /// Channels scaled to `[0, 1]`
+    fn to_unit(self) -> [f64; 3] {
+        [self.r, self.g, self.b].map(|c| c as f64 / 255.)
+    }
+
+    /// Color from channels in `[0, 1]`, clamping the ones out of gamut
+    fn from_unit(c: [f64; 3]) -> RGB {
+        let [r, g, b] = c.map(|v| (v.clamp(0., 1.) * 255.).round() as u8);
+        RGB { r, g, b }
+    }
+}
+
+impl FromStr for RGB {
+    type Err = ColorParseError;
+
+    /// Parse `#RGB`, `#RRGGBB` and `#RRGGBBAA` hex colors, with the `#` being optional, or a
+    /// CSS color name
+    fn from_str(s: &str) -> Result<RGB, ColorParseError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ColorParseError::Empty);
+        }
+        if let Some(digits) = s.strip_prefix('#') {
+            return RGB::from_hex_digits(digits);
+        }
+        let name = s.to_ascii_lowercase();
+        if let Ok(i) = CSS_COLORS.binary_search_by_key(&name.as_str(), |(n, _)| n) {
+            let v = CSS_COLORS[i].1;
+            return Ok(RGB {
+                r: (v >> 16) as u8,
+                g: (v >> 8) as u8,
+                b: v as u8,
+            });
+        }
+        if s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return RGB::from_hex_digits(s);
         }
+        Err(ColorParseError::UnknownName(s.to_string()))
     }
 }
 
-fn get_v_delta(c1: RGB, c2: RGB, d: u32) -> [f64; 3] {
-    let r_inc: f64 = (c2.r as f64 - c1.r as f64) / d as f64;
-    let g_inc: f64 = (c2.g as f64 - c1.g as f64) / d as f64;
-    let b_inc: f64 = (c2.b as f64 - c1.b as f64) / d as f64;
-    [r_inc, g_inc, b_inc]
+impl TryFrom<&str> for RGB {
+    type Error = ColorParseError;
+
+    fn try_from(s: &str) -> Result<RGB, ColorParseError> {
+        s.parse()
+    }
 }
 
-pub fn new_linear_palette(c1: RGB, c2: RGB, c3: RGB, c4: RGB, n: u32) -> Vec<RGB> {
-    let colors = [c1, c2, c3, c4];
-    let n_colors: u32 = 4;
-    let mut palette: Vec<RGB> = vec![];
-    // number of interpolation points between provided colors
-    let m: u32 = (n - n_colors) / (n_colors - 1);
-    // extra points to the last interpolation segment if previous computation is not exact
-    let r: u32 = (n - n_colors) % (n_colors - 1);
-
-    // compute interpolation of values m times for each channel
-    for i_seg in 0..(n_colors - 2) {
-        palette.push(colors[i_seg as usize]);
-        let ci1 = colors[i_seg as usize];
-        let ci2 = colors[(i_seg + 1) as usize];
-        let v_delta: [f64; 3] = get_v_delta(ci1, ci2, m + 2);
-        for i in 1..(m + 1) {
-            palette.push(RGB {
-                r: (ci1.r as f64 + v_delta[0] * (i as f64)) as u8,
-                g: (ci1.g as f64 + v_delta[1] * (i as f64)) as u8,
-                b: (ci1.b as f64 + v_delta[2] * (i as f64)) as u8,
-            })
-        }
-    }
-
-    // last segment with r
-    palette.push(colors[2]);
-    let v_delta: [f64; 3] = get_v_delta(colors[2], colors[3], m + r + 2);
-    for i in 1..(m + r + 1) {
-        palette.push(RGB {
-            r: (colors[2].r as f64 + v_delta[0] * (i as f64)) as u8,
-            g: (colors[2].g as f64 + v_delta[1] * (i as f64)) as u8,
-            b: (colors[2].b as f64 + v_delta[2] * (i as f64)) as u8,
+impl std::fmt::Display for RGB {
+    /// Hex representation `#RRGGBB`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+/// Color space in which gradients blend two stops
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Interpolation {
+    /// Raw sRGB bytes
+    Srgb,
+    /// sRGB converted to linear light
+    LinearRgb,
+    /// Perceptually uniform OKLab
+    Oklab,
+    /// Polar OKLab, following the shortest path around the hue circle
+    Oklch,
+    /// Hue, saturation and value of the sRGB color, following the shortest hue path
+    Hsv,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 5] = [
+        Interpolation::Srgb,
+        Interpolation::LinearRgb,
+        Interpolation::Oklab,
+        Interpolation::Oklch,
+        Interpolation::Hsv,
+    ];
+
+    /// Next color space in `ALL`, wrapping around
+    pub fn next(self) -> Interpolation {
+        let i = Interpolation::ALL.iter().position(|m| *m == self).unwrap();
+        Interpolation::ALL[(i + 1) % Interpolation::ALL.len()]
+    }
+}
+
+/// Linear light value of an sRGB encoded channel in `[0, 1]`
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGB encoding of a linear light channel in `[0, 1]`
+pub fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
+fn linear_rgb_to_oklab([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+fn oklab_to_linear_rgb([l, a, b]: [f64; 3]) -> [f64; 3] {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+    ]
+}
+
+/// Hue in degrees, saturation and value of sRGB channels in `[0, 1]`
+fn rgb_to_hsv([r, g, b]: [f64; 3]) -> [f64; 3] {
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let h = if delta == 0. {
+        0.
+    } else if max == r {
+        60. * ((g - b) / delta).rem_euclid(6.)
+    } else if max == g {
+        60. * ((b - r) / delta + 2.)
+    } else {
+        60. * ((r - g) / delta + 4.)
+    };
+    let s = if max == 0. { 0. } else { delta / max };
+    [h, s, max]
+}
+
+fn hsv_to_rgb([h, s, v]: [f64; 3]) -> [f64; 3] {
+    let c = v * s;
+    let hp = h.rem_euclid(360.) / 60.;
+    let x = c * (1. - (hp % 2. - 1.).abs());
+    let [r, g, b] = match hp as u32 {
+        0 => [c, x, 0.],
+        1 => [x, c, 0.],
+        2 => [0., c, x],
+        3 => [0., x, c],
+        4 => [x, 0., c],
+        _ => [c, 0., x],
+    };
+    [r + v - c, g + v - c, b + v - c]
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Interpolate between two hues in degrees along the shortest arc
+fn lerp_hue(a: f64, b: f64, t: f64) -> f64 {
+    let delta = (b - a + 540.).rem_euclid(360.) - 180.;
+    (a + delta * t).rem_euclid(360.)
+}
+
+/// Palette used by the CLI renders and the live explorer
+pub fn default_gradient() -> Gradient {
+    Gradient::evenly_spaced(&[
+        RGB::hex("E810DD"),
+        RGB::hex("33B242"),
+        RGB::hex("E81000"),
+        RGB::hex("1A246D"),
+    ])
+    .expect("evenly spaced stops are always valid")
+}
+
+/// `default_gradient` sampled with `n` colors
+pub fn default_palette(n: u32) -> Vec<RGB> {
+    default_gradient().palette(n)
+}
+
+/// Color at the fractional position `index` of the palette, linearly interpolated between the
+/// two closest entries. Positions out of the palette are clamped to its ends.
+pub fn palette_color(palette: &[RGB], index: f64) -> RGB {
+    let last = palette.len() - 1;
+    let index = index.clamp(0., last as f64);
+    let i = index.floor() as usize;
+    if i >= last {
+        return palette[last];
+    }
+    let t = index - i as f64;
+    palette[i].lerp(palette[i + 1], t)
+}
+
+/// Same as `palette_color` with the sRGB channels in `[0, 1]`, without rounding the blend
+/// between two palette entries to 8 bits
+pub fn palette_color_unit(palette: &[RGB], index: f64) -> [f64; 3] {
+    let last = palette.len() - 1;
+    let index = index.clamp(0., last as f64);
+    let i = (index.floor() as usize).min(last);
+    let (c1, c2) = (palette[i].to_unit(), palette[(i + 1).min(last)].to_unit());
+    let t = index - i as f64;
+    [0, 1, 2].map(|k| lerp(c1[k], c2[k], t))
+}
+
+/// Errors produced when building a `Gradient` from invalid stops
+#[derive(Debug, Clone, PartialEq)]
+pub enum GradientError {
+    /// A gradient needs at least one color
+    NoStops,
+    /// Stop positions must lie in `[0, 1]`
+    PositionOutOfRange(f64),
+    /// Stop positions must be in non-decreasing order
+    UnsortedStops,
+}
+
+impl std::fmt::Display for GradientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GradientError::NoStops => write!(f, "gradient has no color stops"),
+            GradientError::PositionOutOfRange(p) => {
+                write!(f, "color stop position {} is outside of [0, 1]", p)
+            }
+            GradientError::UnsortedStops => {
+                write!(f, "color stop positions are not in increasing order")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GradientError {}
+
+/// Color placed at `position` in `[0, 1]` along a gradient
+#[derive(Debug, Copy, Clone)]
+pub struct ColorStop {
+    pub position: f64,
+    pub color: RGB,
+}
+
+/// How a gradient is sampled outside of `[0, 1]`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Extend {
+    /// Keep the color of the closest end
+    Clamp,
+    /// Start again from the first stop
+    Repeat,
+    /// Go back and forth between both ends
+    Mirror,
+}
+
+/// Gradient between any number of positioned color stops, blended in `interpolation`
+#[derive(Debug, Clone)]
+pub struct Gradient {
+    stops: Vec<ColorStop>,
+    pub extend: Extend,
+    pub interpolation: Interpolation,
+}
+
+impl Gradient {
+    /// Gradient through `stops`, which must be sorted by position and lie in `[0, 1]`
+    pub fn new(stops: Vec<ColorStop>) -> Result<Gradient, GradientError> {
+        if stops.is_empty() {
+            return Err(GradientError::NoStops);
+        }
+        if let Some(stop) = stops.iter().find(|s| !(0. ..=1.).contains(&s.position)) {
+            return Err(GradientError::PositionOutOfRange(stop.position));
+        }
+        if stops.windows(2).any(|w| w[0].position > w[1].position) {
+            return Err(GradientError::UnsortedStops);
+        }
+        Ok(Gradient {
+            stops,
+            extend: Extend::Clamp,
+            interpolation: Interpolation::Srgb,
         })
     }
-    palette.push(colors[3]);
 
-    palette
+    /// Gradient with `colors` spread at equal distances from 0 to 1
+    pub fn evenly_spaced(colors: &[RGB]) -> Result<Gradient, GradientError> {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Gradient::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, &color)| ColorStop {
+                    position: i as f64 / last,
+                    color,
+                })
+                .collect(),
+        )
+    }
+
+    pub fn with_extend(mut self, extend: Extend) -> Gradient {
+        self.extend = extend;
+        self
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Gradient {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        &self.stops
+    }
+
+    /// Color at the position `t`, folded into `[0, 1]` according to `extend`
+    pub fn sample(&self, t: f64) -> RGB {
+        let t = match self.extend {
+            Extend::Clamp => t.clamp(0., 1.),
+            Extend::Repeat => t.rem_euclid(1.),
+            Extend::Mirror => {
+                let m = t.rem_euclid(2.);
+                if m > 1. {
+                    2. - m
+                } else {
+                    m
+                }
+            }
+        };
+
+        let first = self.stops[0];
+        if t <= first.position {
+            return first.color;
+        }
+        for w in self.stops.windows(2) {
+            let (s1, s2) = (w[0], w[1]);
+            if t <= s2.position {
+                let width = s2.position - s1.position;
+                if width <= 0. {
+                    return s2.color;
+                }
+                let t = (t - s1.position) / width;
+                return s1.color.mix(s2.color, t, self.interpolation);
+            }
+        }
+        self.stops[self.stops.len() - 1].color
+    }
+
+    /// `n` colors sampled at equal distances from 0 to 1, both ends included
+    pub fn palette(&self, n: u32) -> Vec<RGB> {
+        let last = n.saturating_sub(1).max(1) as f64;
+        (0..n).map(|i| self.sample(i as f64 / last)).collect()
+    }
+}
+
+/// Palette of `n` colors going through `c1`, `c2`, `c3` and `c4` at equal distances
+pub fn new_linear_palette(c1: RGB, c2: RGB, c3: RGB, c4: RGB, n: u32) -> Vec<RGB> {
+    Gradient::evenly_spaced(&[c1, c2, c3, c4])
+        .expect("evenly spaced stops are always valid")
+        .palette(n)
 }
@@ -0,0 +1,133 @@
+use rayon::prelude::*;
+
+/// Rows of the strips traced independently
+const STRIP_HEIGHT: u32 = 32;
+
+/// Escape values of a `width` x `height` grid in row-major order, computed by boundary
+/// tracing: starting from the image border, only the pixels next to a pixel of a different
+/// value are computed, which follows the edges of the regions of equal value. The pixels left
+/// inside the regions are then flood-filled. The image is cut into horizontal strips, traced
+/// in parallel when `parallel` is set.
+///
+/// `value(x, y)` is the value of a single pixel and only the values for which `fillable` holds
+/// are flood-filled, the others have every neighbor computed. Like `subdivision::subdivide`,
+/// the fill relies on the regions of equal value having no holes: an island inside a fillable
+/// region that does not reach the border of its strip is never traced and is filled over.
+pub fn trace(
+    (width, height): (u32, u32),
+    parallel: bool,
+    value: impl Fn(u32, u32) -> f64 + Sync,
+    fillable: impl Fn(f64) -> bool + Sync,
+) -> Vec<f64> {
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+    let mut values = vec![f64::NAN; (width * height) as usize];
+    let strip_len = (STRIP_HEIGHT * width) as usize;
+    let trace_strip = |(i, values): (usize, &mut [f64])| {
+        Strip {
+            width: width as usize,
+            y0: i as u32 * STRIP_HEIGHT,
+            queued: vec![false; values.len()],
+            queue: vec![],
+            values,
+            value: &value,
+            fillable: &fillable,
+        }
+        .trace()
+    };
+    if parallel {
+        values
+            .par_chunks_mut(strip_len)
+            .enumerate()
+            .for_each(trace_strip);
+    } else {
+        values
+            .chunks_mut(strip_len)
+            .enumerate()
+            .for_each(trace_strip);
+    }
+    values
+}
+
+/// Rows of the image starting at `y0`. Values not computed yet are NaN, escape values never are.
+struct Strip<'a, F, G> {
+    width: usize,
+    y0: u32,
+    values: &'a mut [f64],
+    queued: Vec<bool>,
+    queue: Vec<usize>,
+    value: &'a F,
+    fillable: &'a G,
+}
+
+impl<F: Fn(u32, u32) -> f64, G: Fn(f64) -> bool> Strip<'_, F, G> {
+    fn trace(&mut self) {
+        let (w, h) = (self.width, self.values.len() / self.width);
+        for x in 0..w {
+            self.push(x);
+            self.push((h - 1) * w + x);
+        }
+        for y in 0..h {
+            self.push(y * w);
+            self.push(y * w + w - 1);
+        }
+        while let Some(p) = self.queue.pop() {
+            self.scan(p);
+        }
+
+        // Every pixel left is surrounded by computed pixels of a single value, and the first
+        // column is computed
+        for p in 0..self.values.len() {
+            if self.values[p].is_nan() {
+                self.values[p] = self.values[p - 1];
+            }
+        }
+    }
+
+    fn load(&mut self, p: usize) -> f64 {
+        if self.values[p].is_nan() {
+            let (x, y) = (p % self.width, p / self.width);
+            self.values[p] = (self.value)(x as u32, self.y0 + y as u32);
+        }
+        self.values[p]
+    }
+
+    fn push(&mut self, p: usize) {
+        if !self.queued[p] {
+            self.queued[p] = true;
+            self.queue.push(p);
+        }
+    }
+
+    /// Compute the pixel `p` and its side neighbors, and queue the neighbors that lie across an
+    /// edge so the edge is followed
+    fn scan(&mut self, p: usize) {
+        let (w, h) = (self.width, self.values.len() / self.width);
+        let (x, y) = (p % w, p / w);
+        let center = self.load(p);
+        let fill = (self.fillable)(center);
+        let mut differs = |q: usize| !fill || self.load(q) != center;
+        let l = x > 0 && differs(p - 1);
+        let r = x + 1 < w && differs(p + 1);
+        let u = y > 0 && differs(p - w);
+        let d = y + 1 < h && differs(p + w);
+
+        let neighbors = [
+            (l, -1, 0),
+            (r, 1, 0),
+            (u, 0, -1),
+            (d, 0, 1),
+            (l || u, -1, -1),
+            (r || u, 1, -1),
+            (l || d, -1, 1),
+            (r || d, 1, 1),
+        ];
+        for (edge, dx, dy) in neighbors {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            if edge && (0..w as isize).contains(&nx) && (0..h as isize).contains(&ny) {
+                self.push(ny as usize * w + nx as usize);
+            }
+        }
+    }
+}
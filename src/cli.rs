@@ -0,0 +1,374 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::parser::ValueSource;
+use clap::{ArgAction, CommandFactory, Parser, ValueEnum};
+
+use num::complex::Complex;
+use num::traits::Zero;
+
+use crate::algcolor::{self, Gradient, Interpolation, RGB};
+use crate::coloring::{Coloring, Interior};
+use crate::floatexp::FloatExp;
+use crate::fractal::{self, Algorithm, Fractal, FractalKind, RenderOptions, Viewport};
+use crate::output::{self, BitDepth, Format, OutputError};
+use crate::palette_file;
+use crate::precision::Decimal;
+use crate::supersampling::{self, Supersampling};
+
+/// Render in PNG a portion of the mandelbrot set
+#[derive(Parser, Debug)]
+// Options can be repeated, the last value wins. This lets the command line replace the options
+// read from --from-image.
+#[command(author, version, about, long_about = None, args_override_self = true)]
+pub struct Args {
+    /// Number of max iterations to set pixel as diverged value
+    #[arg(short, long, default_value = "500", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_iter: u32,
+    /// X (real) coordinate to plot in the center of the image, with as many digits as deep
+    /// zooms need
+    #[arg(short, long, default_value = "-0.9170575")]
+    pub x_coord: Decimal,
+    /// Y (imaginary) coordinate to plot in the center of the image
+    #[arg(short, long, default_value = "-0.277587")]
+    pub y_coord: Decimal,
+    /// Amount of zoom in the fractal, which can go past the range of f64 like 1e400
+    #[arg(short, long, default_value = "35000.", value_parser = parse_positive::<FloatExp>)]
+    pub scale: FloatExp,
+    /// Number of pixels for Weight and Heigh
+    #[arg(short, long, default_value = "1000")]
+    pub img_size: u32,
+    /// Image width in pixels, overrides --img-size
+    #[arg(long)]
+    pub width: Option<u32>,
+    /// Image height in pixels, overrides --img-size. The shorter side spans 4/scale and the
+    /// longer one extends with the aspect ratio
+    #[arg(long)]
+    pub height: Option<u32>,
+    /// Fractal family to render
+    #[arg(short, long, value_enum, default_value = "mandelbrot")]
+    pub fractal: FractalKind,
+    /// Real part of the constant added at each iteration of the Julia set
+    #[arg(long, default_value = "-0.8")]
+    pub julia_re: f64,
+    /// Imaginary part of the constant added at each iteration of the Julia set
+    #[arg(long, default_value = "0.156")]
+    pub julia_im: f64,
+    /// Exponent d of the Multibrot iteration z^d + c, at least 2
+    #[arg(short, long, default_value = "3", value_parser = clap::value_parser!(u32).range(2..))]
+    pub power: u32,
+    /// Color with the integer iteration count instead of the smooth one, showing the bands
+    #[arg(long)]
+    pub discrete: bool,
+    /// Color with the smooth iteration count, the default. Undoes a --discrete read with
+    /// --from-image
+    #[arg(long, overrides_with = "discrete")]
+    pub smooth: bool,
+    /// Comma separated list of colors (hex or CSS names), or path to a .ggr, .map, .json or
+    /// .toml palette file
+    #[arg(long, value_parser = palette_file::parse_palette)]
+    pub palette: Option<Gradient>,
+    /// How escape counts are mapped onto the palette
+    #[arg(long, value_enum, default_value = "linear")]
+    pub coloring: Coloring,
+    /// Iterations between two passes through the palette in cyclic coloring. Lower values give
+    /// denser color bands
+    #[arg(long, default_value = "64", value_parser = parse_positive::<f64>)]
+    pub cycle_length: f64,
+    /// Phase of the palette in cyclic coloring, as a fraction of a cycle
+    #[arg(long, default_value = "0")]
+    pub cycle_offset: f64,
+    /// Color space used to blend the palette colors
+    #[arg(long, value_enum, default_value = "srgb")]
+    pub interpolation: Interpolation,
+    /// Samples per pixel along each axis, every pixel is computed from samples² points to
+    /// smooth the aliasing around filaments
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..=16))]
+    pub samples: u32,
+    /// Placement of the samples inside each pixel
+    #[arg(long, value_enum, default_value = "rotated")]
+    pub sample_pattern: supersampling::Pattern,
+    /// Filter blending the samples into the pixel color
+    #[arg(long, value_enum, default_value = "box")]
+    pub filter: supersampling::Filter,
+    /// Only supersample the pixels on edges: the ones next to the set boundary or to a pixel
+    /// whose color differs by more than THRESHOLD (in [0, 1]) in a channel
+    #[arg(long, value_name = "THRESHOLD", num_args = 0..=1, default_missing_value = "0.05")]
+    pub adaptive: Option<f64>,
+    /// Supersample every pixel, the default. Undoes an --adaptive read with --from-image
+    #[arg(long, overrides_with = "adaptive")]
+    pub no_adaptive: bool,
+    /// Iterate the points of the main cardioid and the period-2 bulb instead of detecting them
+    /// analytically, for timing comparisons
+    #[arg(long)]
+    pub no_interior_check: bool,
+    /// Detect the main cardioid and the period-2 bulb analytically, the default. Undoes a
+    /// --no-interior-check read with --from-image
+    #[arg(long, overrides_with = "no_interior_check")]
+    pub interior_check: bool,
+    /// Iterate every point of the set up to the max iterations instead of stopping the orbits
+    /// that repeat themselves
+    #[arg(long)]
+    pub no_periodicity_check: bool,
+    /// Stop the orbits that repeat themselves, the default. Undoes a --no-periodicity-check
+    /// read with --from-image
+    #[arg(long, overrides_with = "no_periodicity_check")]
+    pub periodicity_check: bool,
+    /// Color of the points of the set, `period` shades them by the period of their cycle
+    #[arg(long, value_enum, default_value = "flat")]
+    pub interior: Interior,
+    /// How the escape values are computed, `subdivision` and `boundary-trace` skip the inside
+    /// of uniform regions and `perturbation` speeds up deep zooms
+    #[arg(long, value_enum, default_value = "brute-force")]
+    pub algorithm: Algorithm,
+    /// Iterate every perturbation orbit from the start instead of skipping the iterations
+    /// that the series approximation covers
+    #[arg(long)]
+    pub no_series_approximation: bool,
+    /// Skip the iterations covered by the series approximation, the default. Undoes a
+    /// --no-series-approximation read with --from-image
+    #[arg(long, overrides_with = "no_series_approximation")]
+    pub series_approximation: bool,
+    /// Image file to write, its format is chosen from the extension: png, jpg, jpeg, tif, tiff,
+    /// webp, bmp or ppm
+    #[arg(short, long, default_value = "output.png")]
+    pub output: PathBuf,
+    /// Bits per color channel, 16 is only available for PNG
+    #[arg(long, value_enum, default_value = "8")]
+    pub bit_depth: BitDepth,
+    /// PNG written by this program to take the render parameters from. Options given on the
+    /// command line replace the stored ones
+    #[arg(long)]
+    pub from_image: Option<PathBuf>,
+}
+
+fn parse_positive<T: FromStr + PartialOrd + Zero>(s: &str) -> Result<T, String> {
+    match s.parse::<T>() {
+        Ok(v) if v > T::zero() => Ok(v),
+        _ => Err(format!("{} is not a positive number", s)),
+    }
+}
+
+/// Name of a `ValueEnum` variant on the command line
+fn value_name(value: impl ValueEnum) -> String {
+    value.to_possible_value().unwrap().get_name().to_string()
+}
+
+impl Args {
+    /// Parse the command line. With `--from-image`, the options that are not given are read
+    /// from the metadata of that image.
+    pub fn parse_with_image() -> Args {
+        let args = Args::parse();
+        let Some(path) = &args.from_image else {
+            return args;
+        };
+        let metadata = output::read_metadata(path)
+            .unwrap_or_else(|e| Args::command().error(clap::error::ErrorKind::Io, e).exit());
+        Args::from_metadata(&metadata, std::env::args_os()).unwrap_or_else(|e| e.exit())
+    }
+
+    /// Parse the command line `args`, starting from the options stored in `metadata` by
+    /// `Args::metadata`. Keys that are not options are ignored, and an --img-size on the
+    /// command line also replaces the stored --width and --height.
+    pub fn from_metadata(
+        metadata: &[(String, String)],
+        args: impl IntoIterator<Item = OsString>,
+    ) -> Result<Args, clap::Error> {
+        let command = Args::command();
+        let args: Vec<OsString> = args.into_iter().collect();
+        let resized = command
+            .clone()
+            .try_get_matches_from(&args)?
+            .value_source("img_size")
+            == Some(ValueSource::CommandLine);
+        let mut stored: Vec<OsString> = args.first().cloned().into_iter().collect();
+        for (key, value) in metadata {
+            if resized && (key == "width" || key == "height") {
+                continue;
+            }
+            let Some(arg) = command
+                .get_arguments()
+                .find(|a| a.get_long() == Some(key.as_str()))
+            else {
+                continue;
+            };
+            match arg.get_action() {
+                ArgAction::SetTrue if value == "true" => stored.push(format!("--{}", key).into()),
+                ArgAction::SetTrue => {}
+                _ => stored.push(format!("--{}={}", key, value).into()),
+            }
+        }
+        Args::try_parse_from(stored.into_iter().chain(args.into_iter().skip(1)))
+    }
+
+    /// Parameters needed to render the same image again, keyed by option name
+    pub fn metadata(&self) -> Vec<(String, String)> {
+        let viewport = self.viewport();
+        let palette = self
+            .palette
+            .clone()
+            .unwrap_or_else(algcolor::default_gradient);
+        // Square images keep --img-size so it can still be changed on the command line
+        let size = if viewport.width == viewport.height {
+            vec![("img-size", viewport.width.to_string())]
+        } else {
+            vec![
+                ("width", viewport.width.to_string()),
+                ("height", viewport.height.to_string()),
+            ]
+        };
+        let metadata = [
+            ("Software", env!("CARGO_PKG_NAME").to_string()),
+            ("fractal", value_name(self.fractal)),
+            ("x-coord", self.x_coord.to_string()),
+            ("y-coord", self.y_coord.to_string()),
+            ("scale", self.scale.to_string()),
+            ("max-iter", self.max_iter.to_string()),
+        ]
+        .into_iter()
+        .chain(size)
+        .chain([
+            ("julia-re", self.julia_re.to_string()),
+            ("julia-im", self.julia_im.to_string()),
+            ("power", self.power.to_string()),
+            ("discrete", self.discrete.to_string()),
+            ("coloring", value_name(self.coloring)),
+            ("interior", value_name(self.interior)),
+            ("cycle-length", self.cycle_length.to_string()),
+            ("cycle-offset", self.cycle_offset.to_string()),
+            ("interpolation", value_name(self.interpolation)),
+            ("samples", self.samples.to_string()),
+            ("sample-pattern", value_name(self.sample_pattern)),
+            ("filter", value_name(self.filter)),
+            ("no-interior-check", self.no_interior_check.to_string()),
+            (
+                "no-periodicity-check",
+                self.no_periodicity_check.to_string(),
+            ),
+            ("algorithm", value_name(self.algorithm)),
+            (
+                "no-series-approximation",
+                self.no_series_approximation.to_string(),
+            ),
+            ("bit-depth", value_name(self.bit_depth)),
+        ])
+        .chain(self.adaptive.map(|t| ("adaptive", t.to_string())))
+        .chain([("palette", palette_file::to_json(&palette))]);
+        metadata
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()
+    }
+
+    pub fn fractal(&self) -> Box<dyn Fractal> {
+        self.fractal.build(
+            Complex {
+                re: self.julia_re,
+                im: self.julia_im,
+            },
+            self.power,
+        )
+    }
+
+    /// Palette with one color per iteration
+    pub fn palette(&self) -> Vec<RGB> {
+        self.palette
+            .clone()
+            .unwrap_or_else(algcolor::default_gradient)
+            .with_interpolation(self.interpolation)
+            .palette(self.max_iter)
+    }
+
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            max_iter: self.max_iter,
+            smooth: !self.discrete,
+            coloring: self.coloring,
+            cycle_length: self.cycle_length,
+            cycle_offset: self.cycle_offset,
+            supersampling: Supersampling {
+                samples: self.samples,
+                pattern: self.sample_pattern,
+                filter: self.filter,
+                adaptive_threshold: self.adaptive,
+            },
+            interior_check: !self.no_interior_check,
+            periodicity_check: !self.no_periodicity_check,
+            interior: self.interior,
+            algorithm: self.algorithm,
+            series_approximation: !self.no_series_approximation,
+        }
+    }
+
+    pub fn output_format(&self) -> Result<Format, OutputError> {
+        Format::from_path(&self.output, self.bit_depth)
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport::with_center(
+            &self.x_coord,
+            &self.y_coord,
+            self.scale,
+            self.width.unwrap_or(self.img_size),
+            self.height.unwrap_or(self.img_size),
+        )
+    }
+}
+
+/// Render the image described by the command line and save it, printing what the render did.
+/// `parallel` computes the pixels on every core.
+pub fn run(parallel: bool) {
+    let args = Args::parse_with_image();
+    let format = args.output_format().unwrap_or_else(|e| {
+        Args::command()
+            .error(clap::error::ErrorKind::ValueValidation, e)
+            .exit()
+    });
+    let viewport = args.viewport();
+    let fractal = args.fractal();
+    let options = args.render_options();
+
+    let palette = args.palette();
+    if let Some(bits) = viewport.precision_bits() {
+        let iterated = if options.algorithm == Algorithm::Perturbation {
+            "the reference orbit"
+        } else {
+            "every pixel"
+        };
+        println!(
+            "deep zoom: iterating {} in fixed point with {} fractional bits",
+            iterated, bits
+        );
+    }
+    let reference = fractal::perturbation_reference(&viewport, fractal.as_ref(), &options);
+    if let Some(reference) = reference.as_ref().filter(|_| options.series_approximation) {
+        println!(
+            "series approximation: skipped {} of {} iterations, estimated error {:.1e} pixels",
+            reference.skipped(),
+            reference.iterations(),
+            reference.series_error()
+        );
+    }
+
+    let (colors, refined) = fractal::render_colors_with(
+        &viewport,
+        fractal.as_ref(),
+        &options,
+        &palette,
+        reference.as_ref(),
+        parallel,
+    );
+    if options.supersampling.count() > 1 {
+        println!(
+            "supersampled {} of {} pixels",
+            refined,
+            viewport.width * viewport.height
+        );
+    }
+    let size = (viewport.width, viewport.height);
+    let metadata = args.metadata();
+    if let Err(e) = output::save(&args.output, format, size, &colors, &metadata) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,159 @@
+use crate::algcolor::{self, RGB};
+use crate::fractal::{self, RenderOptions};
+
+/// How escape counts are mapped onto the palette
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Coloring {
+    /// One palette entry per iteration
+    Linear,
+    /// Spread the counts of the view evenly over the whole palette, using their cumulative
+    /// distribution
+    Histogram,
+    /// Go back and forth through the palette every `cycle_length` iterations, independently of
+    /// `max_iter`
+    Cyclic,
+}
+
+impl Coloring {
+    pub const ALL: [Coloring; 3] = [Coloring::Linear, Coloring::Histogram, Coloring::Cyclic];
+
+    /// Next mode in `ALL`, wrapping around
+    pub fn next(self) -> Coloring {
+        let i = Coloring::ALL.iter().position(|c| *c == self).unwrap();
+        Coloring::ALL[(i + 1) % Coloring::ALL.len()]
+    }
+}
+
+/// Color of the points of the set
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Interior {
+    /// Last color of the palette
+    Flat,
+    /// A darkened palette color picked from the period of the cycle found by the periodicity
+    /// check, so the components of the set stand apart. Points with no known period stay flat.
+    Period,
+}
+
+/// Brightness of the interior colors of `Interior::Period`, in linear light
+const PERIOD_SHADE: f64 = 0.4;
+
+/// sRGB color with channels in `[0, 1]` of the interior points with the given `period`
+fn period_color(palette: &[RGB], period: u32) -> [f64; 3] {
+    // Consecutive periods land far apart on the palette
+    let t = (period as f64 * 0.618_033_988_749_895).fract();
+    let color = algcolor::palette_color_unit(palette, t * (palette.len() - 1) as f64);
+    color.map(|c| algcolor::linear_to_srgb(algcolor::srgb_to_linear(c) * PERIOD_SHADE))
+}
+
+/// Position in `[0, 1]` of the count `n` in a palette cycling every `length` iterations,
+/// shifted by `offset` cycles. Odd cycles run the palette backwards so there is no seam.
+pub fn cyclic_position(n: f64, length: f64, offset: f64) -> f64 {
+    let p = ((n - 1.) / length + offset).rem_euclid(2.);
+    if p > 1. {
+        2. - p
+    } else {
+        p
+    }
+}
+
+/// Fractional palette index of every escape count, for a palette of `palette_len` colors
+fn palette_indices(values: &[f64], options: &RenderOptions, palette_len: usize) -> Vec<f64> {
+    let last = (palette_len - 1) as f64;
+    match options.coloring {
+        Coloring::Linear => values.iter().map(|n| n - 1.).collect(),
+        Coloring::Histogram => {
+            let cdf = Histogram::new(values, options.max_iter);
+            values.iter().map(|&n| cdf.position(n) * last).collect()
+        }
+        Coloring::Cyclic => values
+            .iter()
+            .map(|&n| {
+                let t = if n >= options.max_iter as f64 {
+                    1.
+                } else {
+                    cyclic_position(n, options.cycle_length, options.cycle_offset)
+                };
+                t * last
+            })
+            .collect(),
+    }
+}
+
+/// Colors of a buffer of escape counts, as returned by `fractal::escape_values`, with the sRGB
+/// channels in `[0, 1]`
+pub fn colorize_unit(values: &[f64], options: &RenderOptions, palette: &[RGB]) -> Vec<[f64; 3]> {
+    palette_indices(values, options, palette.len())
+        .into_iter()
+        .zip(values)
+        .map(
+            |(i, &n)| match fractal::interior_period(n, options.max_iter) {
+                Some(period) if options.interior == Interior::Period => {
+                    period_color(palette, period)
+                }
+                _ => algcolor::palette_color_unit(palette, i),
+            },
+        )
+        .collect()
+}
+
+/// Same as `colorize_unit` with 8 bit channels
+pub fn colorize(values: &[f64], options: &RenderOptions, palette: &[RGB]) -> Vec<[u8; 3]> {
+    to_rgb8(&colorize_unit(values, options, palette))
+}
+
+/// Colors with channels in `[0, 1]` quantized to 8 bits
+pub fn to_rgb8(colors: &[[f64; 3]]) -> Vec<[u8; 3]> {
+    colors
+        .iter()
+        .map(|c| c.map(|v| (v.clamp(0., 1.) * 255.).round() as u8))
+        .collect()
+}
+
+/// Colors with channels in `[0, 1]` quantized to 16 bits
+pub fn to_rgb16(colors: &[[f64; 3]]) -> Vec<[u16; 3]> {
+    colors
+        .iter()
+        .map(|c| c.map(|v| (v.clamp(0., 1.) * 65535.).round() as u16))
+        .collect()
+}
+
+/// Cumulative distribution of the escape counts of the points outside of the set
+pub struct Histogram {
+    /// `cumulative[i]` is the fraction of escaped points with a count below `i + 1`
+    cumulative: Vec<f64>,
+    max_iter: u32,
+}
+
+impl Histogram {
+    /// Distribution of `values`, with one bin per integer iteration count
+    pub fn new(values: &[f64], max_iter: u32) -> Histogram {
+        let mut counts = vec![0u64; max_iter as usize];
+        for &n in values.iter().filter(|&&n| n < max_iter as f64) {
+            counts[n as usize] += 1;
+        }
+        let total = counts.iter().sum::<u64>().max(1) as f64;
+
+        let mut cumulative = Vec::with_capacity(counts.len() + 1);
+        let mut sum = 0;
+        cumulative.push(0.);
+        for count in counts {
+            sum += count;
+            cumulative.push(sum as f64 / total);
+        }
+        Histogram {
+            cumulative,
+            max_iter,
+        }
+    }
+
+    /// Position in `[0, 1]` of the count `n` in the distribution. Fractional counts are
+    /// interpolated inside their bin and points of the set are placed at the end.
+    pub fn position(&self, n: f64) -> f64 {
+        if n >= self.max_iter as f64 {
+            return 1.;
+        }
+        let bin = n as usize;
+        let t = n - bin as f64;
+        self.cumulative[bin] + (self.cumulative[bin + 1] - self.cumulative[bin]) * t
+    }
+}
@@ -0,0 +1,328 @@
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use std::str::FromStr;
+
+use num::complex::Complex;
+use num::traits::{Num, One, Zero};
+
+/// Bits of the f64 exponent bias
+const BIAS: i64 = 1023;
+
+/// Largest decimal exponent accepted by the parser, in absolute value. It is far past any zoom
+/// that can be rendered and keeps `FloatExp::pow10` from looping for long.
+const MAX_DECIMAL_EXPONENT: i64 = 100_000;
+
+/// Float with the 53-bit mantissa of a f64 and a 64-bit exponent, `mantissa * 2^exponent`, for
+/// the pixel offsets of zooms whose pixels are smaller than the f64 exponent reaches. The
+/// mantissa is in `[1, 2)` in absolute value, or zero.
+///
+/// It implements the numeric traits of `num`, so `Complex<FloatExp>` has the usual arithmetic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FloatExp {
+    mantissa: f64,
+    exponent: i64,
+}
+
+/// `2^e` for `e` in the range of normal f64 exponents
+fn pow2(e: i64) -> f64 {
+    debug_assert!((1 - BIAS..=BIAS).contains(&e));
+    f64::from_bits(((e + BIAS) as u64) << 52)
+}
+
+impl FloatExp {
+    /// `mantissa * 2^exponent`, for any finite `mantissa`
+    pub fn new(mantissa: f64, exponent: i64) -> FloatExp {
+        if mantissa == 0. || !mantissa.is_finite() {
+            return FloatExp {
+                mantissa: if mantissa == 0. { 0. } else { mantissa },
+                exponent: 0,
+            };
+        }
+        let (mantissa, exponent) = if mantissa.abs() < f64::MIN_POSITIVE {
+            // Subnormal, scaled into the normal range first
+            (mantissa * pow2(64), exponent - 64)
+        } else {
+            (mantissa, exponent)
+        };
+        let bits = mantissa.to_bits();
+        let e = ((bits >> 52) & 0x7ff) as i64 - BIAS;
+        FloatExp {
+            mantissa: f64::from_bits(bits & !(0x7ff << 52) | ((BIAS as u64) << 52)),
+            exponent: exponent + e,
+        }
+    }
+
+    /// Mantissa in `[1, 2)` in absolute value, or zero
+    pub fn mantissa(&self) -> f64 {
+        self.mantissa
+    }
+
+    pub fn exponent(&self) -> i64 {
+        self.exponent
+    }
+
+    /// Nearest f64, zero or infinite past its range
+    pub fn to_f64(&self) -> f64 {
+        let e = self.exponent;
+        if e > BIAS {
+            self.mantissa * f64::INFINITY
+        } else if e < -1100 {
+            self.mantissa * 0.
+        } else {
+            // In two steps, the result can be subnormal
+            self.mantissa * pow2(e / 2) * pow2(e - e / 2)
+        }
+    }
+
+    pub fn abs(&self) -> FloatExp {
+        FloatExp {
+            mantissa: self.mantissa.abs(),
+            exponent: self.exponent,
+        }
+    }
+
+    /// Base 2 logarithm, as a f64 since it is much smaller than the number
+    pub fn log2(&self) -> f64 {
+        self.exponent as f64 + self.mantissa.log2()
+    }
+
+    pub fn sqrt(&self) -> FloatExp {
+        let odd = self.exponent.rem_euclid(2);
+        FloatExp::new(
+            (self.mantissa * (1 + odd) as f64).sqrt(),
+            (self.exponent - odd) / 2,
+        )
+    }
+
+    pub fn powi(&self, n: i32) -> FloatExp {
+        let mut result = FloatExp::one();
+        let mut base = if n < 0 {
+            FloatExp::one() / *self
+        } else {
+            *self
+        };
+        let mut n = n.unsigned_abs();
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+        result
+    }
+
+    /// `10^e`
+    fn pow10(e: i64) -> FloatExp {
+        let ten = FloatExp::from(10.);
+        let mut result = FloatExp::one();
+        // Powers of 10 up to 10^22 are exact in f64, so most of the product is exact
+        for _ in 0..e.unsigned_abs() / 22 {
+            result = result * FloatExp::from(1e22);
+        }
+        result = result * ten.powi((e.unsigned_abs() % 22) as i32);
+        if e < 0 {
+            FloatExp::one() / result
+        } else {
+            result
+        }
+    }
+}
+
+impl From<f64> for FloatExp {
+    fn from(x: f64) -> FloatExp {
+        FloatExp::new(x, 0)
+    }
+}
+
+impl Add for FloatExp {
+    type Output = FloatExp;
+
+    fn add(self, other: FloatExp) -> FloatExp {
+        if self.is_zero() {
+            return other;
+        }
+        if other.is_zero() {
+            return self;
+        }
+        let (large, small) = if self.exponent >= other.exponent {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let shift = large.exponent - small.exponent;
+        if shift > 64 {
+            return large;
+        }
+        FloatExp::new(
+            large.mantissa + small.mantissa * pow2(-shift),
+            large.exponent,
+        )
+    }
+}
+
+impl Sub for FloatExp {
+    type Output = FloatExp;
+
+    fn sub(self, other: FloatExp) -> FloatExp {
+        self + -other
+    }
+}
+
+impl Mul for FloatExp {
+    type Output = FloatExp;
+
+    fn mul(self, other: FloatExp) -> FloatExp {
+        FloatExp::new(
+            self.mantissa * other.mantissa,
+            self.exponent + other.exponent,
+        )
+    }
+}
+
+impl Div for FloatExp {
+    type Output = FloatExp;
+
+    fn div(self, other: FloatExp) -> FloatExp {
+        FloatExp::new(
+            self.mantissa / other.mantissa,
+            self.exponent - other.exponent,
+        )
+    }
+}
+
+impl Rem for FloatExp {
+    type Output = FloatExp;
+
+    /// Remainder of the division truncated towards zero, like `%` on f64
+    fn rem(self, other: FloatExp) -> FloatExp {
+        let quotient = self / other;
+        // Quotients past 2^53 have no fractional part
+        let truncated = if quotient.exponent >= 53 {
+            quotient
+        } else if quotient.exponent < 0 {
+            FloatExp::zero()
+        } else {
+            FloatExp::new((quotient.mantissa * pow2(quotient.exponent)).trunc(), 0)
+        };
+        self - truncated * other
+    }
+}
+
+impl Neg for FloatExp {
+    type Output = FloatExp;
+
+    fn neg(self) -> FloatExp {
+        FloatExp {
+            mantissa: -self.mantissa,
+            exponent: self.exponent,
+        }
+    }
+}
+
+impl PartialOrd for FloatExp {
+    fn partial_cmp(&self, other: &FloatExp) -> Option<Ordering> {
+        (*self - *other).mantissa.partial_cmp(&0.)
+    }
+}
+
+impl Zero for FloatExp {
+    fn zero() -> FloatExp {
+        FloatExp {
+            mantissa: 0.,
+            exponent: 0,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.mantissa == 0.
+    }
+}
+
+impl One for FloatExp {
+    fn one() -> FloatExp {
+        FloatExp {
+            mantissa: 1.,
+            exponent: 0,
+        }
+    }
+}
+
+impl Num for FloatExp {
+    type FromStrRadixErr = ParseFloatExpError;
+
+    fn from_str_radix(s: &str, radix: u32) -> Result<FloatExp, ParseFloatExpError> {
+        if radix != 10 {
+            return Err(ParseFloatExpError(s.to_string()));
+        }
+        s.parse()
+    }
+}
+
+/// Error returned when a string is not a float
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFloatExpError(String);
+
+impl std::fmt::Display for ParseFloatExpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid number {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseFloatExpError {}
+
+impl FromStr for FloatExp {
+    type Err = ParseFloatExpError;
+
+    /// Parse the f64 syntax, with decimal exponents past the range of f64 like `1e400` up to
+    /// `MAX_DECIMAL_EXPONENT` in absolute value
+    fn from_str(s: &str) -> Result<FloatExp, ParseFloatExpError> {
+        let error = || ParseFloatExpError(s.to_string());
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i64>().map_err(|_| error())?),
+            None => (s, 0),
+        };
+        if !(-MAX_DECIMAL_EXPONENT..=MAX_DECIMAL_EXPONENT).contains(&exponent) {
+            return Err(error());
+        }
+        let mantissa = mantissa.parse::<f64>().map_err(|_| error())?;
+        if !mantissa.is_finite() {
+            return Err(error());
+        }
+        // Numbers in the range of normal f64 are parsed exactly by f64
+        let x = s.parse::<f64>().map_err(|_| error())?;
+        if x.is_normal() || mantissa == 0. {
+            return Ok(FloatExp::from(x));
+        }
+        Ok(FloatExp::from(mantissa) * FloatExp::pow10(exponent))
+    }
+}
+
+impl std::fmt::Display for FloatExp {
+    /// Same as f64 inside its range, in scientific notation past it
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.exponent.abs() < 1000 {
+            return write!(f, "{}", self.to_f64());
+        }
+        let log10 = self.abs().log2() * std::f64::consts::LOG10_2;
+        let exponent = log10.floor() as i64;
+        let mantissa = (*self / FloatExp::pow10(exponent)).to_f64();
+        write!(f, "{}e{}", mantissa, exponent)
+    }
+}
+
+/// Complex number with `FloatExp` parts
+pub type ComplexExp = Complex<FloatExp>;
+
+pub fn complex_from_f64(z: Complex<f64>) -> ComplexExp {
+    Complex::new(z.re.into(), z.im.into())
+}
+
+/// Nearest f64 complex number
+pub fn complex_to_f64(z: ComplexExp) -> Complex<f64> {
+    Complex::new(z.re.to_f64(), z.im.to_f64())
+}
+
+pub fn norm(z: ComplexExp) -> FloatExp {
+    z.norm_sqr().sqrt()
+}
@@ -0,0 +1,1058 @@
+use num::complex::Complex;
+use num::traits::{One, Zero};
+use rayon::prelude::*;
+
+use crate::algcolor::RGB;
+use crate::boundary;
+use crate::coloring::{self, Coloring, Interior};
+use crate::floatexp::{self, ComplexExp, FloatExp};
+use crate::perturbation::{self, Reference};
+use crate::precision::{self, ComplexFixed, Decimal, Fixed};
+use crate::subdivision;
+use crate::supersampling::{self, Supersampling};
+
+/// Portion of the complex plane mapped onto an image of `width` x `height` pixels
+#[derive(Debug, Clone)]
+pub struct Viewport {
+    /// Center, rounded to f64
+    pub center_x: f64,
+    pub center_y: f64,
+    pub scale: FloatExp,
+    pub width: u32,
+    pub height: u32,
+    /// Center in fixed point when the pixels are too small for f64, see `precision_bits`
+    pub precise_center: Option<ComplexFixed>,
+}
+
+/// Pixels closer than this many f64 steps of the center are rendered in fixed point. Orbits
+/// add `c` to values of `|z|` up to 2, so the step is taken at a magnitude of at least 1.
+const MIN_ULPS_PER_PIXEL: f64 = 64.;
+
+/// Fractional bits kept in fixed point below the pixel size, absorbing the rounding errors
+/// that the iterations amplify
+const GUARD_BITS: u32 = 32;
+
+/// Pixels smaller than this leave too few f64 exponents below them for the rounding of the
+/// perturbation deltas, which are then iterated in `FloatExp`
+const MIN_F64_PIXEL_SIZE: f64 = 1e-290;
+
+impl Viewport {
+    pub fn new(center_x: f64, center_y: f64, scale: f64, width: u32, height: u32) -> Viewport {
+        Viewport::with_center(
+            &center_x.into(),
+            &center_y.into(),
+            scale.into(),
+            width,
+            height,
+        )
+    }
+
+    /// Viewport around a center given with any number of digits
+    pub fn with_center(
+        center_x: &Decimal,
+        center_y: &Decimal,
+        scale: FloatExp,
+        width: u32,
+        height: u32,
+    ) -> Viewport {
+        let mut viewport = Viewport {
+            center_x: center_x.to_f64(),
+            center_y: center_y.to_f64(),
+            scale,
+            width,
+            height,
+            precise_center: None,
+        };
+        viewport.precise_center = viewport
+            .precision_bits()
+            .map(|bits| Complex::new(center_x.to_fixed(bits), center_y.to_fixed(bits)));
+        viewport
+    }
+
+    /// Side of a pixel in the complex plane. The shorter image side spans `4/scale`, like
+    /// `min_screen_size` in shader.wgsl, and the longer one extends with the aspect ratio.
+    pub fn pixel_size(&self) -> FloatExp {
+        FloatExp::from(4.) / (self.scale * FloatExp::from(self.width.min(self.height) as f64))
+    }
+
+    /// Fractional bits of the fixed-point coordinates when the spacing of f64 values around
+    /// the center is too coarse for the pixel size, `None` when f64 is enough
+    pub fn precision_bits(&self) -> Option<u32> {
+        let pixel_size = self.pixel_size();
+        let magnitude = self.center_x.abs().max(self.center_y.abs()).max(1.);
+        if pixel_size >= FloatExp::from(magnitude * f64::EPSILON * MIN_ULPS_PER_PIXEL) {
+            return None;
+        }
+        Some((-pixel_size.log2()).ceil() as u32 + GUARD_BITS)
+    }
+
+    /// Complex coordinate of the top left corner of the pixel `(x, y)`, the samples of the
+    /// pixel are placed from there by `Supersampling::offset`
+    pub fn pixel_to_complex(&self, x: u32, y: u32) -> Complex<f64> {
+        self.point_to_complex(x as f64, y as f64)
+    }
+
+    /// Whether the pixels are too small for the f64 exponent, see `perturbation::Reference`
+    pub fn needs_floatexp(&self) -> bool {
+        self.pixel_size() < FloatExp::from(MIN_F64_PIXEL_SIZE)
+    }
+
+    /// Offset from the center of a point given in pixels, which can fall inside a pixel
+    pub fn point_offset(&self, x: f64, y: f64) -> Complex<f64> {
+        let pixel_size = self.pixel_size().to_f64();
+        Complex {
+            re: (x - self.width as f64 / 2.) * pixel_size,
+            im: (y - self.height as f64 / 2.) * pixel_size,
+        }
+    }
+
+    /// Same as `point_offset` without the limits of the f64 exponent
+    pub fn point_offset_exp(&self, x: f64, y: f64) -> ComplexExp {
+        let pixels = Complex::new(x - self.width as f64 / 2., y - self.height as f64 / 2.);
+        floatexp::complex_from_f64(pixels) * self.pixel_size()
+    }
+
+    /// Complex coordinate of a point given in pixels, which can fall inside a pixel
+    pub fn point_to_complex(&self, x: f64, y: f64) -> Complex<f64> {
+        Complex::new(self.center_x, self.center_y) + self.point_offset(x, y)
+    }
+
+    /// Fixed-point coordinate of a point given in pixels, when the viewport needs one
+    pub fn point_to_precise(&self, x: f64, y: f64) -> Option<ComplexFixed> {
+        let center = self.precise_center.as_ref()?;
+        let offset =
+            precision::complex_from_floatexp(self.point_offset_exp(x, y), center.re.bits());
+        Some(precision::complex_add(center, &offset))
+    }
+}
+
+/// Escape-time fractal defined by the orbit `z_0 = init(c)`, `z_n+1 = step(z_n, c)`
+pub trait Fractal: Sync {
+    /// First value of the orbit for the pixel coordinate `c`
+    fn init(&self, c: Complex<f64>) -> Complex<f64>;
+    /// Next value of the orbit
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64>;
+    /// Same as `init` in fixed point, for deep zooms
+    fn init_precise(&self, c: &ComplexFixed) -> ComplexFixed {
+        let zero = Fixed::zero(c.re.bits());
+        Complex::new(zero.clone(), zero)
+    }
+    /// Same as `step` in fixed point, for deep zooms
+    fn step_precise(&self, z: &ComplexFixed, c: &ComplexFixed) -> ComplexFixed;
+    /// `step(z + dz, c + dc) - step(z, c)` written without the cancellation of that
+    /// difference, which perturbation rendering iterates for small `dz` and `dc`. `None` when
+    /// the fractal has no such formula.
+    fn step_delta(
+        &self,
+        _z: Complex<f64>,
+        _dz: Complex<f64>,
+        _dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        None
+    }
+    /// Same as `step_delta` in `FloatExp`, for the pixels too small for f64. Fractals with a
+    /// `step_delta` have both.
+    fn step_delta_exp(
+        &self,
+        _z: Complex<f64>,
+        _dz: ComplexExp,
+        _dc: ComplexExp,
+    ) -> Option<ComplexExp> {
+        None
+    }
+    /// Coefficients of `dc`, `dc^2`, ... in the delta of the next iteration, from the ones of
+    /// the delta `dz` at the reference value `z`, for the series approximation of perturbation
+    /// rendering. `None` when the delta is not a polynomial in `dc`.
+    fn step_series(&self, _z: Complex<f64>, _series: &[ComplexExp]) -> Option<Vec<ComplexExp>> {
+        None
+    }
+    /// Whether the orbit has left the circle of radius `bailout` and the point is considered diverged
+    fn escaped(&self, z: Complex<f64>, bailout: f64) -> bool {
+        z.norm() > bailout
+    }
+    /// Growth rate of `|z|` once the orbit has escaped, used by the smooth iteration count
+    fn degree(&self) -> f64 {
+        2.
+    }
+    /// Period of the cycle the orbit of `c` falls into, when `c` is known to be in the set
+    /// without iterating it
+    fn known_period(&self, _c: Complex<f64>) -> Option<u32> {
+        None
+    }
+}
+
+/// 1 if `c` lies in the main cardioid of the Mandelbrot set and 2 if it lies in the period-2
+/// bulb, the two largest regions of its interior
+pub fn cardioid_or_bulb_period(c: Complex<f64>) -> Option<u32> {
+    let x = c.re - 0.25;
+    let y2 = c.im * c.im;
+    let q = x * x + y2;
+    if q * (q + x) <= 0.25 * y2 {
+        Some(1)
+    } else if (c.re + 1.) * (c.re + 1.) + y2 <= 0.0625 {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Series of `(z + dz)^2 - z^2 = 2 z dz + dz^2`, shared by the quadratic fractals
+fn quadratic_series(z: Complex<f64>, series: &[ComplexExp]) -> Vec<ComplexExp> {
+    let twice = floatexp::complex_from_f64(2. * z);
+    let square = perturbation::series_product(series, series);
+    series
+        .iter()
+        .zip(square)
+        .map(|(&a, a2)| twice * a + a2)
+        .collect()
+}
+
+/// `z = z*z + c` starting from zero
+#[derive(Debug, Copy, Clone)]
+pub struct Mandelbrot;
+
+impl Fractal for Mandelbrot {
+    fn init(&self, _c: Complex<f64>) -> Complex<f64> {
+        Complex { re: 0., im: 0. }
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        z * z + c
+    }
+
+    fn step_precise(&self, z: &ComplexFixed, c: &ComplexFixed) -> ComplexFixed {
+        precision::complex_add(&precision::complex_square(z), c)
+    }
+
+    fn step_delta(
+        &self,
+        z: Complex<f64>,
+        dz: Complex<f64>,
+        dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        Some((2. * z + dz) * dz + dc)
+    }
+
+    fn step_delta_exp(
+        &self,
+        z: Complex<f64>,
+        dz: ComplexExp,
+        dc: ComplexExp,
+    ) -> Option<ComplexExp> {
+        Some((floatexp::complex_from_f64(2. * z) + dz) * dz + dc)
+    }
+
+    fn step_series(&self, z: Complex<f64>, series: &[ComplexExp]) -> Option<Vec<ComplexExp>> {
+        let mut next = quadratic_series(z, series);
+        next[0] = next[0] + Complex::one();
+        Some(next)
+    }
+
+    fn known_period(&self, c: Complex<f64>) -> Option<u32> {
+        cardioid_or_bulb_period(c)
+    }
+}
+
+/// `z = z*z + k` starting from the pixel coordinate, for a fixed parameter `k`
+#[derive(Debug, Copy, Clone)]
+pub struct Julia {
+    pub k: Complex<f64>,
+}
+
+impl Fractal for Julia {
+    fn init(&self, c: Complex<f64>) -> Complex<f64> {
+        c
+    }
+
+    fn step(&self, z: Complex<f64>, _c: Complex<f64>) -> Complex<f64> {
+        z * z + self.k
+    }
+
+    fn init_precise(&self, c: &ComplexFixed) -> ComplexFixed {
+        c.clone()
+    }
+
+    fn step_precise(&self, z: &ComplexFixed, _c: &ComplexFixed) -> ComplexFixed {
+        let k = precision::complex_from_f64(self.k, z.re.bits());
+        precision::complex_add(&precision::complex_square(z), &k)
+    }
+
+    fn step_delta(
+        &self,
+        z: Complex<f64>,
+        dz: Complex<f64>,
+        _dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        Some((2. * z + dz) * dz)
+    }
+
+    fn step_delta_exp(
+        &self,
+        z: Complex<f64>,
+        dz: ComplexExp,
+        _dc: ComplexExp,
+    ) -> Option<ComplexExp> {
+        Some((floatexp::complex_from_f64(2. * z) + dz) * dz)
+    }
+
+    fn step_series(&self, z: Complex<f64>, series: &[ComplexExp]) -> Option<Vec<ComplexExp>> {
+        Some(quadratic_series(z, series))
+    }
+}
+
+/// `z = (|re(z)| + i|im(z)|)^2 + c` starting from zero
+#[derive(Debug, Copy, Clone)]
+pub struct BurningShip;
+
+impl Fractal for BurningShip {
+    fn init(&self, _c: Complex<f64>) -> Complex<f64> {
+        Complex { re: 0., im: 0. }
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let z = Complex {
+            re: z.re.abs(),
+            im: z.im.abs(),
+        };
+        z * z + c
+    }
+
+    fn step_precise(&self, z: &ComplexFixed, c: &ComplexFixed) -> ComplexFixed {
+        let z = Complex::new(z.re.abs(), z.im.abs());
+        precision::complex_add(&precision::complex_square(&z), c)
+    }
+}
+
+/// `z = conj(z)^2 + c` starting from zero, also known as Mandelbar
+#[derive(Debug, Copy, Clone)]
+pub struct Tricorn;
+
+impl Fractal for Tricorn {
+    fn init(&self, _c: Complex<f64>) -> Complex<f64> {
+        Complex { re: 0., im: 0. }
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let z = z.conj();
+        z * z + c
+    }
+
+    fn step_precise(&self, z: &ComplexFixed, c: &ComplexFixed) -> ComplexFixed {
+        let z = Complex::new(z.re.clone(), -&z.im);
+        precision::complex_add(&precision::complex_square(&z), c)
+    }
+
+    fn step_delta(
+        &self,
+        z: Complex<f64>,
+        dz: Complex<f64>,
+        dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        Some((2. * z.conj() + dz.conj()) * dz.conj() + dc)
+    }
+
+    fn step_delta_exp(
+        &self,
+        z: Complex<f64>,
+        dz: ComplexExp,
+        dc: ComplexExp,
+    ) -> Option<ComplexExp> {
+        Some((floatexp::complex_from_f64(2. * z.conj()) + dz.conj()) * dz.conj() + dc)
+    }
+}
+
+/// `z = z^power + c` starting from zero
+#[derive(Debug, Copy, Clone)]
+pub struct Multibrot {
+    pub power: u32,
+}
+
+impl Fractal for Multibrot {
+    fn init(&self, _c: Complex<f64>) -> Complex<f64> {
+        Complex { re: 0., im: 0. }
+    }
+
+    fn step(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        z.powu(self.power) + c
+    }
+
+    fn step_precise(&self, z: &ComplexFixed, c: &ComplexFixed) -> ComplexFixed {
+        let bits = z.re.bits();
+        let one = Complex::new(Fixed::from_f64(1., bits), Fixed::zero(bits));
+        let power = (0..self.power).fold(one, |p, _| precision::complex_mul(&p, z));
+        precision::complex_add(&power, c)
+    }
+
+    /// `(z + dz)^d - z^d` is the sum of `binomial(d, k) z^(d-k) dz^k` for `k` from 1 to `d`,
+    /// evaluated with Horner's method in `dz`
+    fn step_delta(
+        &self,
+        z: Complex<f64>,
+        dz: Complex<f64>,
+        dc: Complex<f64>,
+    ) -> Option<Complex<f64>> {
+        let d = self.power;
+        let mut binomial = 1.;
+        let mut sum = Complex::new(0., 0.);
+        for k in (1..=d).rev() {
+            // binomial(d, k), from binomial(d, k + 1)
+            if k < d {
+                binomial *= (k + 1) as f64 / (d - k) as f64;
+            }
+            sum = sum * dz + binomial * z.powu(d - k);
+        }
+        Some(sum * dz + dc)
+    }
+
+    fn step_delta_exp(
+        &self,
+        z: Complex<f64>,
+        dz: ComplexExp,
+        dc: ComplexExp,
+    ) -> Option<ComplexExp> {
+        let d = self.power;
+        let mut binomial = 1.;
+        let mut sum = ComplexExp::zero();
+        for k in (1..=d).rev() {
+            if k < d {
+                binomial *= (k + 1) as f64 / (d - k) as f64;
+            }
+            sum = sum * dz + floatexp::complex_from_f64(binomial * z.powu(d - k));
+        }
+        Some(sum * dz + dc)
+    }
+
+    /// Same expansion as `step_delta` with the powers of `dz` taken as series
+    fn step_series(&self, z: Complex<f64>, series: &[ComplexExp]) -> Option<Vec<ComplexExp>> {
+        let d = self.power;
+        let mut binomial = 1.;
+        let mut power = series.to_vec();
+        let mut next = vec![ComplexExp::zero(); series.len()];
+        for k in 1..=d {
+            // binomial(d, k), from binomial(d, k - 1)
+            binomial *= (d - k + 1) as f64 / k as f64;
+            let factor = floatexp::complex_from_f64(binomial * z.powu(d - k));
+            for (n, &p) in next.iter_mut().zip(&power) {
+                *n = *n + factor * p;
+            }
+            power = perturbation::series_product(&power, series);
+        }
+        next[0] = next[0] + Complex::one();
+        Some(next)
+    }
+
+    fn degree(&self) -> f64 {
+        self.power as f64
+    }
+
+    fn known_period(&self, c: Complex<f64>) -> Option<u32> {
+        cardioid_or_bulb_period(c).filter(|_| self.power == 2)
+    }
+}
+
+/// Built-in fractal families, in the order used by the `fractal_type` field of the GPU uniform
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum FractalKind {
+    Mandelbrot,
+    Julia,
+    BurningShip,
+    Tricorn,
+    Multibrot,
+}
+
+impl FractalKind {
+    pub const ALL: [FractalKind; 5] = [
+        FractalKind::Mandelbrot,
+        FractalKind::Julia,
+        FractalKind::BurningShip,
+        FractalKind::Tricorn,
+        FractalKind::Multibrot,
+    ];
+
+    /// Build the fractal of this family. `julia_k` is only used by Julia and `power` by Multibrot
+    pub fn build(self, julia_k: Complex<f64>, power: u32) -> Box<dyn Fractal> {
+        match self {
+            FractalKind::Mandelbrot => Box::new(Mandelbrot),
+            FractalKind::Julia => Box::new(Julia { k: julia_k }),
+            FractalKind::BurningShip => Box::new(BurningShip),
+            FractalKind::Tricorn => Box::new(Tricorn),
+            FractalKind::Multibrot => Box::new(Multibrot { power }),
+        }
+    }
+
+    /// Next family in `ALL`, wrapping around
+    pub fn next(self) -> FractalKind {
+        let i = FractalKind::ALL.iter().position(|k| *k == self).unwrap();
+        FractalKind::ALL[(i + 1) % FractalKind::ALL.len()]
+    }
+}
+
+/// Number of iterations needed for the orbit of `c` to escape, capped to `max_iter`
+pub fn escape_time<F: Fractal + ?Sized>(fractal: &F, c: Complex<f64>, max_iter: u32) -> u32 {
+    let mut z = fractal.init(c);
+    let mut n = 0;
+    loop {
+        z = fractal.step(z, c);
+        n += 1;
+
+        if fractal.escaped(z, 2.) || n >= max_iter {
+            break n;
+        }
+    }
+}
+
+/// Bailout radius used by the smooth iteration count. A large radius keeps the log-log
+/// correction accurate, so the fractional part follows the real escape speed.
+pub const SMOOTH_BAILOUT: f64 = 256.;
+
+/// How the orbit of a point ends
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Orbit {
+    /// `z_n`, the first value of the orbit outside of the bailout circle, was reached at
+    /// iteration `n < max_iter`
+    Escaped { n: u32, z: Complex<f64> },
+    /// The orbit stayed bounded for `max_iter` iterations, or repeated itself with the given
+    /// period
+    Bounded { period: Option<u32> },
+}
+
+/// Iterate the orbit of `c` until it leaves the circle of radius `bailout` or reaches
+/// `max_iter` iterations.
+///
+/// With a `tolerance`, Brent's cycle detection also stops the orbits that come back within
+/// `tolerance` of a previous value: a value is saved at every power of two iterations and
+/// compared with the following ones, so a cycle of period `p` is found once the saved value
+/// lies on it and the window has grown past `p`. The reported period is then reduced to its
+/// smallest divisor that brings the orbit back within `tolerance`, see `smallest_period`.
+pub fn iterate<F: Fractal + ?Sized>(
+    fractal: &F,
+    c: Complex<f64>,
+    max_iter: u32,
+    bailout: f64,
+    tolerance: Option<f64>,
+) -> Orbit {
+    let tolerance_sqr = tolerance.map(|t| t * t);
+    let mut z = fractal.init(c);
+    let mut saved = z;
+    let mut window = 1;
+    let mut steps = 0;
+    for n in 1..=max_iter {
+        z = fractal.step(z, c);
+
+        if n >= max_iter {
+            break;
+        }
+        if fractal.escaped(z, bailout) {
+            return Orbit::Escaped { n, z };
+        }
+        if let Some(tolerance_sqr) = tolerance_sqr {
+            steps += 1;
+            if (z - saved).norm_sqr() <= tolerance_sqr {
+                let period = smallest_period(
+                    &z,
+                    steps,
+                    |z| fractal.step(*z, c),
+                    |a, b| (a - b).norm_sqr() <= tolerance_sqr,
+                );
+                return Orbit::Bounded {
+                    period: Some(period),
+                };
+            }
+            if steps == window {
+                saved = z;
+                window *= 2;
+                steps = 0;
+            }
+        }
+    }
+    Orbit::Bounded { period: None }
+}
+
+/// Smallest divisor `d` of the `period` found by the cycle detection such that the orbit
+/// starting at `z` comes back `close` to it after `d` steps. An orbit still converging to its
+/// cycle can first match after a multiple of the period.
+fn smallest_period<Z>(
+    z: &Z,
+    period: u32,
+    step: impl Fn(&Z) -> Z,
+    close: impl Fn(&Z, &Z) -> bool,
+) -> u32 {
+    (1..period)
+        .filter(|&d| period.is_multiple_of(d))
+        .find(|&d| {
+            let w = (1..d).fold(step(z), |w, _| step(&w));
+            close(&w, z)
+        })
+        .unwrap_or(period)
+}
+
+/// Same as `iterate` in fixed point, for the points of deep zooms. The escaped value is
+/// rounded to f64, which is enough for the smooth count.
+pub fn iterate_precise<F: Fractal + ?Sized>(
+    fractal: &F,
+    c: &ComplexFixed,
+    max_iter: u32,
+    bailout: f64,
+    tolerance: Option<FloatExp>,
+) -> Orbit {
+    let tolerance_sqr = tolerance.map(|t| t * t);
+    let mut z = fractal.init_precise(c);
+    let mut saved = z.clone();
+    let mut window = 1;
+    let mut steps = 0;
+    for n in 1..=max_iter {
+        z = fractal.step_precise(&z, c);
+
+        if n >= max_iter {
+            break;
+        }
+        let rounded = precision::complex_to_f64(&z);
+        if fractal.escaped(rounded, bailout) {
+            return Orbit::Escaped { n, z: rounded };
+        }
+        if let Some(tolerance_sqr) = tolerance_sqr {
+            steps += 1;
+            // The difference is taken before rounding, the values themselves only differ
+            // past the precision of f64
+            let close = |a: &ComplexFixed, b: &ComplexFixed| {
+                let difference = precision::complex_to_floatexp(&precision::complex_sub(a, b));
+                difference.norm_sqr() <= tolerance_sqr
+            };
+            if close(&z, &saved) {
+                let period = smallest_period(&z, steps, |z| fractal.step_precise(z, c), close);
+                return Orbit::Bounded {
+                    period: Some(period),
+                };
+            }
+            if steps == window {
+                saved = z.clone();
+                window *= 2;
+                steps = 0;
+            }
+        }
+    }
+    Orbit::Bounded { period: None }
+}
+
+/// Fractional count of an orbit that escaped the circle of radius `bailout` with the value
+/// `z` at iteration `n`. See `smooth_escape_time`.
+fn smooth_count<F: Fractal + ?Sized>(fractal: &F, n: u32, z: Complex<f64>, bailout: f64) -> f64 {
+    let nu = (z.norm().ln() / bailout.ln()).ln() / fractal.degree().ln();
+    n as f64 + 1. - nu
+}
+
+/// Fractional number of iterations needed for the orbit of `c` to escape the circle of
+/// radius `bailout`. Points that do not escape return `max_iter`.
+///
+/// The value is `n + 1 - ln(ln|z_n| / ln(bailout)) / ln(degree)`, which lies in `(n, n + 1]`
+/// and varies continuously across the bands of the integer count.
+pub fn smooth_escape_time<F: Fractal + ?Sized>(
+    fractal: &F,
+    c: Complex<f64>,
+    max_iter: u32,
+    bailout: f64,
+) -> f64 {
+    match iterate(fractal, c, max_iter, bailout, None) {
+        Orbit::Escaped { n, z } => smooth_count(fractal, n, z, bailout).clamp(1., max_iter as f64),
+        Orbit::Bounded { .. } => max_iter as f64,
+    }
+}
+
+/// Number of iterations of `z = z*z + c` needed for `z` to escape the radius 2 circle,
+/// capped to `max_iter`
+pub fn mandelbrot_divergence(c: Complex<f64>, max_iter: u32) -> u32 {
+    escape_time(&Mandelbrot, c, max_iter)
+}
+
+/// How the escape values of an image are computed
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Algorithm {
+    /// Iterate every sample
+    BruteForce,
+    /// Mariani-Silver rectangle subdivision, filling the rectangles whose border is in the set
+    /// without iterating their inside. See `subdivision::subdivide`.
+    Subdivision,
+    /// Follow the edge of the set and flood-fill its inside. See `boundary::trace`.
+    BoundaryTrace,
+    /// Iterate the differences to a reference orbit of the view center in f64, which keeps
+    /// deep zooms fast. See `perturbation::Reference`.
+    Perturbation,
+}
+
+/// Parameters of the iteration and coloring shared by every pixel of a render
+#[derive(Debug, Copy, Clone)]
+pub struct RenderOptions {
+    pub max_iter: u32,
+    /// Color with the smooth iteration count, interpolating between palette entries
+    pub smooth: bool,
+    /// How escape counts are mapped onto the palette
+    pub coloring: Coloring,
+    /// Iterations between two passes through the palette in cyclic coloring
+    pub cycle_length: f64,
+    /// Phase of the palette in cyclic coloring, in cycles
+    pub cycle_offset: f64,
+    pub supersampling: Supersampling,
+    /// Skip the iteration of the points that `Fractal::known_period` places in the set
+    pub interior_check: bool,
+    /// Stop the orbits that repeat themselves, see `iterate`
+    pub periodicity_check: bool,
+    /// How the points of the set are colored
+    pub interior: Interior,
+    pub algorithm: Algorithm,
+    /// Start the perturbation orbits past the iterations covered by the series approximation,
+    /// see `Reference::with_series`
+    pub series_approximation: bool,
+}
+
+impl RenderOptions {
+    /// Same options as the defaults of the command line
+    pub const DEFAULT: RenderOptions = RenderOptions {
+        max_iter: 500,
+        smooth: true,
+        coloring: Coloring::Linear,
+        cycle_length: 64.,
+        cycle_offset: 0.,
+        supersampling: Supersampling::NONE,
+        interior_check: true,
+        periodicity_check: true,
+        interior: Interior::Flat,
+        algorithm: Algorithm::BruteForce,
+        series_approximation: true,
+    };
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions::DEFAULT
+    }
+}
+
+/// Distance under which two values of an orbit are considered equal by the periodicity check,
+/// as a fraction of the pixel size. Orbits of points outside of the set can come back close to
+/// themselves before escaping, so the tolerance shrinks with the zoom.
+const PERIODICITY_TOLERANCE: f64 = 1e-3;
+
+/// Escape value of a point of the set. `max_iter` when the period of its orbit is not known,
+/// `max_iter + period` otherwise, so that every value from `max_iter` on is an interior point.
+pub fn interior_value(max_iter: u32, period: Option<u32>) -> f64 {
+    (max_iter + period.unwrap_or(0)) as f64
+}
+
+/// Period stored in an escape value by `interior_value`, if any
+pub fn interior_period(value: f64, max_iter: u32) -> Option<u32> {
+    if value > max_iter as f64 {
+        Some(value as u32 - max_iter)
+    } else {
+        None
+    }
+}
+
+/// Bailout radius of the orbits rendered with `options`
+fn bailout(options: &RenderOptions) -> f64 {
+    if options.smooth {
+        SMOOTH_BAILOUT
+    } else {
+        2.
+    }
+}
+
+/// Reference orbit of the view center when `options` asks for perturbation and the fractal
+/// supports it, with its series approximation when enabled. Other fractals are rendered by
+/// brute force.
+pub fn perturbation_reference(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+) -> Option<Reference> {
+    let zero = Complex::new(0., 0.);
+    if options.algorithm != Algorithm::Perturbation
+        || fractal.step_delta(zero, zero, zero).is_none()
+    {
+        return None;
+    }
+    let reference = Reference::new(fractal, viewport, options.max_iter, bailout(options));
+    if options.series_approximation {
+        Some(reference.with_series(fractal, viewport))
+    } else {
+        Some(reference)
+    }
+}
+
+/// Escape count of the sample `i` of the pixel `(x, y)` of the viewport, smooth or integer
+/// depending on `options`. Points of the set get an `interior_value`, with their period when
+/// `options.interior` shades by period.
+pub fn escape_value(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    (x, y): (u32, u32),
+    i: u32,
+) -> f64 {
+    sample_value(viewport, fractal, options, None, (x, y), i)
+}
+
+/// Same as `escape_value`, following the `reference` orbit when there is one
+fn sample_value(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    reference: Option<&Reference>,
+    (x, y): (u32, u32),
+    i: u32,
+) -> f64 {
+    let (ox, oy) = options.supersampling.offset(x, y, i);
+    let (px, py) = (x as f64 + ox, y as f64 + oy);
+    let max_iter = options.max_iter;
+    let tolerance = options
+        .periodicity_check
+        .then(|| viewport.pixel_size() * FloatExp::from(PERIODICITY_TOLERANCE));
+    let bailout = bailout(options);
+    // Periods are only kept for the shading, the other renders give the whole set one value
+    // that `sample_values` can fill
+    let interior = |period: Option<u32>| {
+        interior_value(
+            max_iter,
+            period.filter(|_| options.interior == Interior::Period),
+        )
+    };
+    // The interior check is skipped in fixed point, f64 cannot tell on which side of the
+    // cardioid such close points are
+    if options.interior_check && viewport.precise_center.is_none() {
+        if let Some(period) = fractal.known_period(viewport.point_to_complex(px, py)) {
+            return interior(Some(period));
+        }
+    }
+    let orbit = if let Some(reference) = reference {
+        reference.iterate(
+            fractal,
+            viewport.point_offset_exp(px, py),
+            max_iter,
+            bailout,
+        )
+    } else if let Some(c) = viewport.point_to_precise(px, py) {
+        iterate_precise(fractal, &c, max_iter, bailout, tolerance)
+    } else {
+        iterate(
+            fractal,
+            viewport.point_to_complex(px, py),
+            max_iter,
+            bailout,
+            tolerance.map(|t| t.to_f64()),
+        )
+    };
+    match orbit {
+        Orbit::Escaped { n, z } if options.smooth => {
+            smooth_count(fractal, n, z, bailout).clamp(1., max_iter as f64)
+        }
+        Orbit::Escaped { n, .. } => n as f64,
+        Orbit::Bounded { period } => interior(period),
+    }
+}
+
+/// Escape value of the `i`th sample of the viewport, in the order of `escape_values`
+fn indexed_escape_value(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    reference: Option<&Reference>,
+    i: u32,
+) -> f64 {
+    let count = options.supersampling.count();
+    let pixel = i / count;
+    sample_value(
+        viewport,
+        fractal,
+        options,
+        reference,
+        (pixel % viewport.width, pixel / viewport.width),
+        i % count,
+    )
+}
+
+/// `f` evaluated on `0..n`, in parallel or in the calling thread
+fn compute(n: u32, parallel: bool, f: impl Fn(u32) -> f64 + Sync + Send) -> Vec<f64> {
+    if parallel {
+        (0..n).into_par_iter().map(f).collect()
+    } else {
+        (0..n).map(f).collect()
+    }
+}
+
+/// Escape values of every sample of the viewport, in the order of `escape_values`, computed
+/// with `options.algorithm`
+fn sample_values(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    reference: Option<&Reference>,
+    parallel: bool,
+) -> Vec<f64> {
+    let count = options.supersampling.count();
+    let n = viewport.width * viewport.height * count;
+    match options.algorithm {
+        Algorithm::BruteForce | Algorithm::Perturbation => compute(n, parallel, |i| {
+            indexed_escape_value(viewport, fractal, options, reference, i)
+        }),
+        // The samples with the same index form a grid of their own, computed separately
+        Algorithm::Subdivision | Algorithm::BoundaryTrace => {
+            let size = (viewport.width, viewport.height);
+            // An escape band can enclose the set and the other bands, and a component of the set
+            // can enclose components of other periods. Only the set without its periods has no
+            // holes, so it is the only region filled.
+            let interior = interior_value(options.max_iter, None);
+            let fillable = |v| v == interior && options.interior == Interior::Flat;
+            let grids: Vec<Vec<f64>> = (0..count)
+                .map(|s| {
+                    let value = |x, y| escape_value(viewport, fractal, options, (x, y), s);
+                    if options.algorithm == Algorithm::Subdivision {
+                        subdivision::subdivide(size, parallel, value, fillable)
+                    } else {
+                        boundary::trace(size, parallel, value, fillable)
+                    }
+                })
+                .collect();
+            (0..n)
+                .map(|i| grids[(i % count) as usize][(i / count) as usize])
+                .collect()
+        }
+    }
+}
+
+/// Escape counts of the viewport in a row-major buffer, with the
+/// `options.supersampling.count()` samples of each pixel next to each other, computing them in
+/// parallel
+pub fn escape_values(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+) -> Vec<f64> {
+    let reference = perturbation_reference(viewport, fractal, options);
+    sample_values(viewport, fractal, options, reference.as_ref(), true)
+}
+
+/// Same as `escape_values` but computing every sample in the calling thread
+pub fn escape_values_single_thread(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+) -> Vec<f64> {
+    let reference = perturbation_reference(viewport, fractal, options);
+    sample_values(viewport, fractal, options, reference.as_ref(), false)
+}
+
+/// Pixel colors of the escape counts of `escape_values`, in sRGB with channels in `[0, 1]`
+pub fn resolve_colors(
+    viewport: &Viewport,
+    values: &[f64],
+    options: &RenderOptions,
+    palette: &[RGB],
+) -> Vec<[f64; 3]> {
+    let colors = coloring::colorize_unit(values, options, palette);
+    options
+        .supersampling
+        .resolve(&colors, viewport.width, viewport.height)
+}
+
+/// Pixel colors of the viewport in sRGB with channels in `[0, 1]`, and the number of pixels
+/// that were supersampled, following the `reference` orbit given by `perturbation_reference`.
+/// Adaptive supersampling renders one sample per pixel first and then only supersamples the
+/// pixels on edges.
+pub fn render_colors_with(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    palette: &[RGB],
+    reference: Option<&Reference>,
+    parallel: bool,
+) -> (Vec<[f64; 3]>, u32) {
+    let supersampling = options.supersampling;
+    let n = viewport.width * viewport.height;
+    let count = supersampling.count();
+    let Some(threshold) = supersampling.adaptive_threshold.filter(|_| count > 1) else {
+        let values = sample_values(viewport, fractal, options, reference, parallel);
+        let refined = if count > 1 { n } else { 0 };
+        return (resolve_colors(viewport, &values, options, palette), refined);
+    };
+
+    let single = RenderOptions {
+        supersampling: Supersampling::NONE,
+        ..*options
+    };
+    let values = sample_values(viewport, fractal, &single, reference, parallel);
+    let colors = coloring::colorize_unit(&values, options, palette);
+    let size = (viewport.width, viewport.height);
+    let edges = supersampling::edge_pixels(&values, &colors, options.max_iter, size, threshold);
+
+    let pixel = |p: u32| (p % viewport.width, p / viewport.width);
+    let edge_values = compute(edges.len() as u32 * count, parallel, |i| {
+        let p = edges[(i / count) as usize];
+        sample_value(viewport, fractal, options, reference, pixel(p), i % count)
+    });
+    // Color every value at once so histogram coloring sees the refined samples too
+    let all_colors = coloring::colorize_unit(&[values, edge_values].concat(), options, palette);
+    let (pixel_colors, edge_colors) = all_colors.split_at(n as usize);
+
+    let mut pixels = pixel_colors.to_vec();
+    for (&p, samples) in edges.iter().zip(edge_colors.chunks(count as usize)) {
+        let (x, y) = pixel(p);
+        pixels[p as usize] = supersampling.resolve_pixel(samples, x, y);
+    }
+    (pixels, edges.len() as u32)
+}
+
+/// Pixel colors of the viewport in sRGB with channels in `[0, 1]`, computing them in
+/// parallel, and the number of pixels that were supersampled
+pub fn render_colors(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    palette: &[RGB],
+) -> (Vec<[f64; 3]>, u32) {
+    let reference = perturbation_reference(viewport, fractal, options);
+    render_colors_with(
+        viewport,
+        fractal,
+        options,
+        palette,
+        reference.as_ref(),
+        true,
+    )
+}
+
+/// Same as `render_colors` but computing every pixel in the calling thread
+pub fn render_colors_single_thread(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    palette: &[RGB],
+) -> (Vec<[f64; 3]>, u32) {
+    let reference = perturbation_reference(viewport, fractal, options);
+    render_colors_with(
+        viewport,
+        fractal,
+        options,
+        palette,
+        reference.as_ref(),
+        false,
+    )
+}
+
+/// Render the viewport into a row-major RGB buffer, computing pixels in parallel
+pub fn render(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    palette: &[RGB],
+) -> Vec<[u8; 3]> {
+    coloring::to_rgb8(&render_colors(viewport, fractal, options, palette).0)
+}
+
+/// Same as `render` but computing every pixel in the calling thread
+pub fn render_single_thread(
+    viewport: &Viewport,
+    fractal: &dyn Fractal,
+    options: &RenderOptions,
+    palette: &[RGB],
+) -> Vec<[u8; 3]> {
+    coloring::to_rgb8(&render_colors_single_thread(viewport, fractal, options, palette).0)
+}
@@ -0,0 +1,67 @@
+// Histogram coloring, appended to shader.wgsl. Needs compute shaders and storage buffers,
+// so it is not available on WebGL.
+
+// Bins of the histogram of escape counts, matching whistogram::HISTOGRAM_BINS
+const HISTOGRAM_BINS: u32 = 4096u;
+
+@group(3) @binding(0)
+var<storage, read_write> escape_values: array<f32>;
+@group(3) @binding(1)
+var<storage, read_write> histogram: array<atomic<u32>>;
+@group(3) @binding(2)
+var<storage, read_write> cumulative: array<f32>;
+
+// Position of the escape count n in the bins, its integer part is the bin index
+fn histogram_position(n: f32) -> f32 {
+    return (n - 1.0) / fractalInfo.max_iter * f32(HISTOGRAM_BINS);
+}
+
+// First pass: escape count of every pixel and histogram of the ones outside of the set
+@compute @workgroup_size(8, 8)
+fn cs_escape_values(@builtin(global_invocation_id) id: vec3<u32>) {
+    let width = u32(screenInfo.img_size_w);
+    let height = u32(screenInfo.img_size_h);
+    if id.x >= width || id.y >= height {
+        return;
+    }
+
+    let n = mandelbrot_divergence(pixel_to_complex(vec2<f32>(id.xy) + 0.5));
+    escape_values[id.y * width + id.x] = n;
+    if n < fractalInfo.max_iter {
+        let bin = min(u32(histogram_position(n)), HISTOGRAM_BINS - 1u);
+        atomicAdd(&histogram[bin], 1u);
+    }
+}
+
+// Second pass: cumulative distribution of the histogram, normalized to [0, 1]
+@compute @workgroup_size(1)
+fn cs_cumulative() {
+    var total = 0u;
+    for (var i = 0u; i < HISTOGRAM_BINS; i++) {
+        total += atomicLoad(&histogram[i]);
+    }
+    var sum = 0u;
+    for (var i = 0u; i < HISTOGRAM_BINS; i++) {
+        sum += atomicLoad(&histogram[i]);
+        cumulative[i] = f32(sum) / f32(max(total, 1u));
+    }
+}
+
+// Render pass: color the escape counts of the first pass with their place in the distribution
+@fragment
+fn fs_histogram(in: VertexOutput) -> @location(0) vec4<f32> {
+    let width = u32(screenInfo.img_size_w);
+    let pixel = vec2<u32>(in.clip_position.xy);
+    let n = escape_values[pixel.y * width + pixel.x];
+    if n >= fractalInfo.max_iter {
+        return palette_sample(1.0);
+    }
+
+    let position = histogram_position(n);
+    let bin = min(u32(position), HISTOGRAM_BINS - 1u);
+    var previous = 0.0;
+    if bin > 0u {
+        previous = cumulative[bin - 1u];
+    }
+    return palette_sample(mix(previous, cumulative[bin], fract(position)));
+}
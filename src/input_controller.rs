@@ -1,12 +1,41 @@
+use instant::Instant;
 use winit::event::*;
 
+use crate::algcolor::Interpolation;
+use crate::coloring::Coloring;
+use crate::fractal::FractalKind;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct FractalInfo {
+    /// Center in double-single: the nearest f32 of each coordinate and the f32 nearest to the
+    /// rest, see `split_f64`
     pos_x: f32,
     pos_y: f32,
+    pos_x_lo: f32,
+    pos_y_lo: f32,
     scale: f32,
-    pad: f32,
+    max_iter: f32,
+    fractal_type: u32,
+    power: u32,
+    julia_re: f32,
+    julia_im: f32,
+    bailout: f32,
+    coloring: u32,
+    cycle_length: f32,
+    cycle_offset: f32,
+    /// Skip the iteration of the main cardioid and the period-2 bulb when not zero
+    interior_check: u32,
+    /// Always 0, hides the double-single intermediates from the optimizations of the shader
+    /// compiler, see `opaque` in shader.wgsl
+    zero: u32,
+}
+
+/// `x` as the sum of the nearest f32 and the f32 nearest to the rest, about 48 bits of its
+/// mantissa
+fn split_f64(x: f64) -> (f32, f32) {
+    let hi = x as f32;
+    (hi, (x - hi as f64) as f32)
 }
 
 #[repr(C)]
@@ -14,43 +43,170 @@ pub struct FractalInfo {
 pub struct ScreenInfo {
     pub img_size_w: f32,
     pub img_size_h: f32,
+    /// Supersampling quality, fragments are computed from samples² points
+    samples: u32,
     pad: f32,
-    pad2: f32,
 }
 
 #[derive(Debug)]
 pub struct InputController {
     pub fractal_info: FractalInfo,
     pub screen_info: ScreenInfo,
+    /// Color space of the palette uploaded to the GPU
+    pub interpolation: Interpolation,
+    /// How escape counts are mapped onto the palette
+    pub coloring: Coloring,
+    /// Center of the view, kept in f64 and sent to the shader split in two f32
+    center: (f64, f64),
+    fractal_kind: FractalKind,
+    /// Iterations chosen by the user, before the auto mode adjusts them to the zoom
+    max_iter: f32,
+    auto_max_iter: bool,
+    /// Shift the cyclic palette at `CYCLE_SPEED`
+    animate_cycle: bool,
+    /// Time of the previous frame, the animation advances with the time elapsed since then
+    last_tick: Instant,
     is_mouse_clicked: bool,
 }
 
+/// Highest supersampling quality of the explorer, in samples per axis
+const MAX_SAMPLES: u32 = 4;
+
+/// Palette cycles advanced every second while the color cycling animation runs
+const CYCLE_SPEED: f32 = 0.3;
+
 impl InputController {
     pub fn new_empty() -> InputController {
         InputController {
             fractal_info: FractalInfo {
                 pos_x: 0.0,
                 pos_y: 0.0,
+                pos_x_lo: 0.0,
+                pos_y_lo: 0.0,
                 scale: 1.0,
-                pad: 0.0,
+                max_iter: 500.0,
+                fractal_type: 0,
+                power: 3,
+                julia_re: -0.8,
+                julia_im: 0.156,
+                bailout: 256.0,
+                coloring: 0,
+                cycle_length: 64.0,
+                cycle_offset: 0.0,
+                interior_check: 1,
+                zero: 0,
             },
             screen_info: ScreenInfo {
                 img_size_w: 100.0,
                 img_size_h: 100.0,
+                samples: 1,
                 pad: 0.0,
-                pad2: 0.0,
             },
+            interpolation: Interpolation::Srgb,
+            coloring: Coloring::Linear,
+            center: (0.0, 0.0),
+            fractal_kind: FractalKind::Mandelbrot,
+            max_iter: 500.0,
+            auto_max_iter: false,
+            animate_cycle: false,
+            last_tick: Instant::now(),
             is_mouse_clicked: false,
         }
     }
 
+    /// Move the view to `(x, y)`, with the precision of double-single in the shader
+    pub fn set_center(&mut self, x: f64, y: f64) {
+        self.center = (x, y);
+        (self.fractal_info.pos_x, self.fractal_info.pos_x_lo) = split_f64(x);
+        (self.fractal_info.pos_y, self.fractal_info.pos_y_lo) = split_f64(y);
+    }
+
+    /// Advance the time dependent state, called once per frame
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
+        if self.animate_cycle {
+            self.fractal_info.cycle_offset =
+                (self.fractal_info.cycle_offset + CYCLE_SPEED * elapsed) % 2.0;
+        }
+    }
+
+    /// Recompute the iterations sent to the shader. In auto mode they grow with the
+    /// logarithm of the zoom, so deep views keep resolving detail.
+    fn update_max_iter(&mut self) {
+        self.fractal_info.max_iter = if self.auto_max_iter {
+            let zoom = self.fractal_info.scale.max(1.0).log2();
+            (self.max_iter * (1.0 + zoom / 8.0)).round()
+        } else {
+            self.max_iter
+        };
+    }
+
+    fn process_key(&mut self, key: VirtualKeyCode) -> bool {
+        match key {
+            VirtualKeyCode::F => {
+                self.fractal_kind = self.fractal_kind.next();
+                self.fractal_info.fractal_type = self.fractal_kind as u32;
+            }
+            VirtualKeyCode::Up => self.max_iter = (self.max_iter * 1.25).round(),
+            VirtualKeyCode::Down => self.max_iter = (self.max_iter / 1.25).round().max(10.0),
+            VirtualKeyCode::Right => self.fractal_info.bailout *= 2.0,
+            VirtualKeyCode::Left => {
+                self.fractal_info.bailout = (self.fractal_info.bailout / 2.0).max(2.0)
+            }
+            VirtualKeyCode::A => self.auto_max_iter = !self.auto_max_iter,
+            VirtualKeyCode::I => {
+                self.fractal_info.interior_check ^= 1;
+                log::info!("interior check: {}", self.fractal_info.interior_check != 0);
+            }
+            VirtualKeyCode::Q => {
+                self.screen_info.samples = self.screen_info.samples % MAX_SAMPLES + 1;
+                log::info!(
+                    "samples: {}x{}",
+                    self.screen_info.samples,
+                    self.screen_info.samples
+                );
+            }
+            VirtualKeyCode::H => {
+                self.coloring = self.coloring.next();
+                self.fractal_info.coloring = self.coloring as u32;
+                log::info!("coloring: {:?}", self.coloring);
+            }
+            VirtualKeyCode::LBracket => {
+                self.fractal_info.cycle_length = (self.fractal_info.cycle_length / 1.5).max(1.0);
+                log::info!("cycle length: {}", self.fractal_info.cycle_length);
+            }
+            VirtualKeyCode::RBracket => {
+                self.fractal_info.cycle_length *= 1.5;
+                log::info!("cycle length: {}", self.fractal_info.cycle_length);
+            }
+            VirtualKeyCode::P => self.animate_cycle = !self.animate_cycle,
+            VirtualKeyCode::C => {
+                self.interpolation = self.interpolation.next();
+                log::info!("palette interpolation: {:?}", self.interpolation);
+            }
+            _ => return false,
+        }
+        self.update_max_iter();
+        log::info!(
+            "max_iter: {} (auto: {}), bailout: {}",
+            self.fractal_info.max_iter,
+            self.auto_max_iter,
+            self.fractal_info.bailout
+        );
+        true
+    }
+
     pub fn process_mouse_events(&mut self, event: &DeviceEvent) -> bool {
         match event {
-            DeviceEvent::MouseMotion { delta } if self.is_mouse_clicked == true => {
-                self.fractal_info.pos_x -=
-                    (6. / self.fractal_info.scale) * (delta.0 as f32 / self.screen_info.img_size_w);
-                self.fractal_info.pos_y -=
-                    (6. / self.fractal_info.scale) * (delta.1 as f32 / self.screen_info.img_size_h);
+            DeviceEvent::MouseMotion { delta } if self.is_mouse_clicked => {
+                let step = 6. / self.fractal_info.scale as f64;
+                let (x, y) = self.center;
+                self.set_center(
+                    x - step * (delta.0 / self.screen_info.img_size_w as f64),
+                    y - step * (delta.1 / self.screen_info.img_size_h as f64),
+                );
                 true
             }
             _ => false,
@@ -62,26 +218,38 @@ impl InputController {
             WindowEvent::MouseWheel { delta, .. } => match delta {
                 MouseScrollDelta::LineDelta(_, y) => {
                     self.fractal_info.scale *= if *y >= 0.0 { 1.1 } else { 0.9 };
+                    self.update_max_iter();
                     true
                 }
                 MouseScrollDelta::PixelDelta(lpos) => {
                     self.fractal_info.scale *= if lpos.y >= 0.0 { 1.1 } else { 0.9 };
+                    self.update_max_iter();
                     true
                 }
             },
-            WindowEvent::MouseInput { button, state, .. } => match button {
-                MouseButton::Left => match state {
-                    ElementState::Pressed => {
-                        self.is_mouse_clicked = true;
-                        true
-                    }
-                    ElementState::Released => {
-                        self.is_mouse_clicked = false;
-                        true
-                    }
-                },
-                _ => false,
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } => match state {
+                ElementState::Pressed => {
+                    self.is_mouse_clicked = true;
+                    true
+                }
+                ElementState::Released => {
+                    self.is_mouse_clicked = false;
+                    true
+                }
             },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => self.process_key(*key),
             _ => false,
         }
     }
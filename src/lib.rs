@@ -1,10 +1,27 @@
 use std::iter;
+pub mod algcolor;
+pub mod boundary;
+pub mod cli;
+pub mod coloring;
+pub mod floatexp;
+pub mod fractal;
 mod input_controller;
+pub mod output;
+pub mod palette_file;
+pub mod perturbation;
+pub mod precision;
+pub mod subdivision;
+pub mod supersampling;
 mod wbuffer;
+mod whistogram;
 mod wpipeline;
+mod wtexture;
 
+use coloring::Coloring;
 use wbuffer::WBuffer;
+use whistogram::WHistogram;
 use wpipeline::WPipeline;
+use wtexture::WTexture;
 
 use winit::{
     event::*,
@@ -27,6 +44,19 @@ struct State {
     input_controller: input_controller::InputController,
     fractal_info_wbuffer: wbuffer::WBuffer,
     screen_info_wbuffer: wbuffer::WBuffer,
+    palette_wtexture: wtexture::WTexture,
+    palette_interpolation: algcolor::Interpolation,
+    /// Histogram coloring passes, only available when the adapter supports compute shaders
+    histogram: Option<WHistogram>,
+}
+
+/// Number of colors uploaded to the GPU palette, the shader interpolates between them
+const PALETTE_SIZE: u32 = 1024;
+
+fn explorer_palette(interpolation: algcolor::Interpolation) -> Vec<algcolor::RGB> {
+    algcolor::default_gradient()
+        .with_interpolation(interpolation)
+        .palette(PALETTE_SIZE)
 }
 
 impl State {
@@ -94,30 +124,59 @@ impl State {
         };
         surface.configure(&device, &config);
 
-        let input_controller = input_controller::InputController::new_empty();
+        let mut input_controller = input_controller::InputController::new_empty();
+        input_controller.screen_info.img_size_w = config.width as f32;
+        input_controller.screen_info.img_size_h = config.height as f32;
 
-        let fractal_info_wbuffer = WBuffer::new_fragment_uniform(
+        // Uniforms are also read by the compute passes of the histogram coloring
+        let fractal_info_wbuffer = WBuffer::new_uniform(
             &device,
             bytemuck::cast_slice(&[input_controller.fractal_info]),
             "FractalInfo",
+            wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
         );
-        let screen_info_wbuffer = WBuffer::new_fragment_uniform(
+        let screen_info_wbuffer = WBuffer::new_uniform(
             &device,
             bytemuck::cast_slice(&[input_controller.screen_info]),
             "ScreenInfo",
+            wgpu::ShaderStages::FRAGMENT | wgpu::ShaderStages::COMPUTE,
+        );
+
+        let palette_interpolation = input_controller.interpolation;
+        let palette_wtexture = WTexture::new_fragment_palette(
+            &device,
+            &queue,
+            &explorer_palette(palette_interpolation),
+            "Palette",
         );
 
+        let bind_groups_layouts = [
+            &fractal_info_wbuffer.bind_group_layout,
+            &screen_info_wbuffer.bind_group_layout,
+            &palette_wtexture.bind_group_layout,
+        ];
         let render_pipeline = WPipeline::new_render_pipeline(
             &device,
-            &[
-                &fractal_info_wbuffer.bind_group_layout,
-                &screen_info_wbuffer.bind_group_layout,
-            ],
+            &bind_groups_layouts,
             include_str!("shader.wgsl").into(),
+            "fs_main",
             config.format,
             "RenderPipeline1",
         );
 
+        let histogram = if WHistogram::is_supported(&adapter) {
+            Some(WHistogram::new(
+                &device,
+                &bind_groups_layouts,
+                config.format,
+                config.width,
+                config.height,
+            ))
+        } else {
+            log::warn!("Compute shaders are not supported, histogram coloring is disabled");
+            None
+        };
+
         Self {
             surface,
             device,
@@ -129,6 +188,9 @@ impl State {
             input_controller,
             fractal_info_wbuffer,
             screen_info_wbuffer,
+            palette_wtexture,
+            palette_interpolation,
+            histogram,
         }
     }
 
@@ -144,6 +206,9 @@ impl State {
             self.surface.configure(&self.device, &self.config);
             self.input_controller.screen_info.img_size_w = self.config.width as f32;
             self.input_controller.screen_info.img_size_h = self.config.height as f32;
+            if let Some(histogram) = &mut self.histogram {
+                histogram.resize(&self.device, self.config.width, self.config.height);
+            }
         }
     }
 
@@ -157,6 +222,7 @@ impl State {
     }
 
     fn update(&mut self) {
+        self.input_controller.tick();
         self.queue.write_buffer(
             &self.fractal_info_wbuffer.buffer,
             0,
@@ -167,6 +233,11 @@ impl State {
             0,
             bytemuck::cast_slice(&[self.input_controller.screen_info]),
         );
+        if self.palette_interpolation != self.input_controller.interpolation {
+            self.palette_interpolation = self.input_controller.interpolation;
+            self.palette_wtexture
+                .write(&self.queue, &explorer_palette(self.palette_interpolation));
+        }
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
@@ -181,6 +252,24 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
+        let bind_groups = [
+            &self.fractal_info_wbuffer.bind_group,
+            &self.screen_info_wbuffer.bind_group,
+            &self.palette_wtexture.bind_group,
+        ];
+        let histogram = self
+            .histogram
+            .as_ref()
+            .filter(|_| self.input_controller.coloring == Coloring::Histogram);
+        if let Some(histogram) = histogram {
+            histogram.compute(
+                &mut encoder,
+                &bind_groups,
+                self.config.width,
+                self.config.height,
+            );
+        }
+
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -200,15 +289,23 @@ impl State {
                 depth_stencil_attachment: None,
             });
 
-            render_pass.set_pipeline(&self.render_pipeline.pipeline);
-            render_pass.set_vertex_buffer(0, self.render_pipeline.vertex_buffer.slice(..));
+            let render_pipeline = match histogram {
+                Some(histogram) => &histogram.render_pipeline,
+                None => &self.render_pipeline,
+            };
+            render_pass.set_pipeline(&render_pipeline.pipeline);
+            render_pass.set_vertex_buffer(0, render_pipeline.vertex_buffer.slice(..));
             render_pass.set_index_buffer(
-                self.render_pipeline.index_buffer.slice(..),
+                render_pipeline.index_buffer.slice(..),
                 wgpu::IndexFormat::Uint16,
             );
-            render_pass.set_bind_group(0, &self.fractal_info_wbuffer.bind_group, &[]);
-            render_pass.set_bind_group(1, &self.screen_info_wbuffer.bind_group, &[]);
-            render_pass.draw_indexed(0..self.render_pipeline.num_indices, 0, 0..1);
+            for (i, bind_group) in bind_groups.iter().enumerate() {
+                render_pass.set_bind_group(i as u32, bind_group, &[]);
+            }
+            if let Some(histogram) = histogram {
+                render_pass.set_bind_group(bind_groups.len() as u32, histogram.bind_group(), &[]);
+            }
+            render_pass.draw_indexed(0..render_pipeline.num_indices, 0, 0..1);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
@@ -264,30 +361,26 @@ pub async fn run() {
             Event::WindowEvent {
                 ref event,
                 window_id,
-            } if window_id == state.window().id() => {
-                if !state.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                                    ..
-                                },
+            } if window_id == state.window().id() && !state.input(event) => match event {
+                WindowEvent::CloseRequested
+                | WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(VirtualKeyCode::Escape),
                             ..
-                        } => *control_flow = ControlFlow::Exit,
-                        WindowEvent::Resized(physical_size) => {
-                            state.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            // new_inner_size is &mut so w have to dereference it twice
-                            state.resize(**new_inner_size);
-                        }
-                        _ => {}
-                    }
+                        },
+                    ..
+                } => *control_flow = ControlFlow::Exit,
+                WindowEvent::Resized(physical_size) => {
+                    state.resize(*physical_size);
                 }
-            }
+                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                    // new_inner_size is &mut so w have to dereference it twice
+                    state.resize(**new_inner_size);
+                }
+                _ => {}
+            },
             Event::DeviceEvent {
                 device_id: _,
                 event,
@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+use image::{ImageBuffer, ImageError, ImageFormat};
+
+use crate::coloring;
+
+/// Bits per color channel of the written image
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum BitDepth {
+    #[value(name = "8")]
+    Eight,
+    /// Only for PNG files
+    #[value(name = "16")]
+    Sixteen,
+}
+
+/// Image file formats written by the renderers
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    /// PNG with 16 bit channels
+    Png16,
+    Jpeg,
+    Tiff,
+    /// Lossless WebP
+    WebP,
+    Bmp,
+    /// Binary PPM (P6)
+    Ppm,
+}
+
+/// Errors produced when choosing the output format or writing the image
+#[derive(Debug)]
+pub enum OutputError {
+    /// The extension of the path does not match any supported format
+    UnknownExtension(PathBuf),
+    /// 16 bit channels were requested for a format other than PNG
+    UnsupportedDepth(PathBuf),
+    Write {
+        path: PathBuf,
+        error: ImageError,
+    },
+    /// The `--from-image` file is not a readable PNG
+    Read {
+        path: PathBuf,
+        error: png::DecodingError,
+    },
+}
+
+impl std::fmt::Display for OutputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputError::UnknownExtension(path) => write!(
+                f,
+                "cannot infer the image format of {}, supported extensions are png, jpg, jpeg, tif, tiff, webp, bmp and ppm",
+                path.display()
+            ),
+            OutputError::UnsupportedDepth(path) => write!(
+                f,
+                "cannot write {} with 16 bit channels, only PNG supports them",
+                path.display()
+            ),
+            OutputError::Write { path, error } => {
+                write!(f, "cannot write {}: {}", path.display(), error)
+            }
+            OutputError::Read { path, error } => {
+                write!(f, "cannot read {}: {}", path.display(), error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+impl Format {
+    /// Format of `path`, chosen from its extension
+    pub fn from_path(path: &Path, depth: BitDepth) -> Result<Format, OutputError> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let format = match extension.as_deref() {
+            Some("png") if depth == BitDepth::Sixteen => return Ok(Format::Png16),
+            Some("png") => Format::Png,
+            Some("jpg" | "jpeg") => Format::Jpeg,
+            Some("tif" | "tiff") => Format::Tiff,
+            Some("webp") => Format::WebP,
+            Some("bmp") => Format::Bmp,
+            Some("ppm") => Format::Ppm,
+            _ => return Err(OutputError::UnknownExtension(path.to_path_buf())),
+        };
+        if depth == BitDepth::Sixteen {
+            return Err(OutputError::UnsupportedDepth(path.to_path_buf()));
+        }
+        Ok(format)
+    }
+}
+
+/// Write the row-major colors of a `width` x `height` image to `path`. Colors are sRGB with
+/// channels in `[0, 1]`. PNG files also store `metadata` as text chunks.
+pub fn save(
+    path: &Path,
+    format: Format,
+    (width, height): (u32, u32),
+    colors: &[[f64; 3]],
+    metadata: &[(String, String)],
+) -> Result<(), OutputError> {
+    let write_error = |error| OutputError::Write {
+        path: path.to_path_buf(),
+        error,
+    };
+
+    if format == Format::Png16 {
+        let data: Vec<u8> = coloring::to_rgb16(colors)
+            .iter()
+            .flatten()
+            .flat_map(|c| c.to_be_bytes())
+            .collect();
+        return write_png(
+            path,
+            (width, height),
+            png::BitDepth::Sixteen,
+            &data,
+            metadata,
+        )
+        .map_err(write_error);
+    }
+
+    let pixels = coloring::to_rgb8(colors);
+    if format == Format::Png {
+        let data: Vec<u8> = pixels.into_iter().flatten().collect();
+        return write_png(path, (width, height), png::BitDepth::Eight, &data, metadata)
+            .map_err(write_error);
+    }
+
+    let image = ImageBuffer::from_fn(width, height, |x, y| {
+        image::Rgb(pixels[(y * width + x) as usize])
+    });
+    let image_format = match format {
+        Format::Png | Format::Png16 => ImageFormat::Png,
+        Format::Jpeg => ImageFormat::Jpeg,
+        Format::Tiff => ImageFormat::Tiff,
+        Format::WebP => ImageFormat::WebP,
+        Format::Bmp => ImageFormat::Bmp,
+        Format::Ppm => {
+            // The default PNM encoder writes PAM files, ask for a binary pixmap instead
+            let file = File::create(path).map_err(|e| write_error(ImageError::IoError(e)))?;
+            let encoder = PnmEncoder::new(BufWriter::new(file))
+                .with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary));
+            return image.write_with_encoder(encoder).map_err(write_error);
+        }
+    };
+    image
+        .save_with_format(path, image_format)
+        .map_err(write_error)
+}
+
+/// RGB PNG with the `metadata` key-value pairs in `tEXt` chunks, before the image data
+fn write_png(
+    path: &Path,
+    (width, height): (u32, u32),
+    depth: png::BitDepth,
+    data: &[u8],
+    metadata: &[(String, String)],
+) -> Result<(), ImageError> {
+    let png_error =
+        |e| ImageError::Encoding(image::error::EncodingError::new(ImageFormat::Png.into(), e));
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(depth);
+    for (key, value) in metadata {
+        encoder
+            .add_text_chunk(key.clone(), value.clone())
+            .map_err(png_error)?;
+    }
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(data).map_err(png_error)?;
+    writer.finish().map_err(png_error)
+}
+
+/// Key-value pairs stored in the text chunks of the PNG at `path`
+pub fn read_metadata(path: &Path) -> Result<Vec<(String, String)>, OutputError> {
+    let read_error = |error| OutputError::Read {
+        path: path.to_path_buf(),
+        error,
+    };
+    let file = File::open(path).map_err(|e| read_error(e.into()))?;
+    let reader = png::Decoder::new(BufReader::new(file))
+        .read_info()
+        .map_err(read_error)?;
+    let info = reader.info();
+
+    let mut metadata = vec![];
+    for chunk in &info.uncompressed_latin1_text {
+        metadata.push((chunk.keyword.clone(), chunk.text.clone()));
+    }
+    for chunk in &info.compressed_latin1_text {
+        metadata.push((chunk.keyword.clone(), chunk.get_text().map_err(read_error)?));
+    }
+    for chunk in &info.utf8_text {
+        metadata.push((chunk.keyword.clone(), chunk.get_text().map_err(read_error)?));
+    }
+    Ok(metadata)
+}
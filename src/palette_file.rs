@@ -0,0 +1,235 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::algcolor::{ColorParseError, ColorStop, Gradient, GradientError, RGB};
+
+/// Errors produced when reading a palette from the command line or a file
+#[derive(Debug)]
+pub enum PaletteError {
+    Io(std::io::Error),
+    Color(ColorParseError),
+    Gradient(GradientError),
+    /// Malformed palette file, with the line where the problem was found
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+}
+
+impl std::fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaletteError::Io(e) => write!(f, "cannot read palette file: {}", e),
+            PaletteError::Color(e) => write!(f, "{}", e),
+            PaletteError::Gradient(e) => write!(f, "{}", e),
+            PaletteError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            PaletteError::Json(e) => write!(f, "invalid JSON palette: {}", e),
+            PaletteError::Toml(e) => write!(f, "invalid TOML palette: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PaletteError {}
+
+impl From<std::io::Error> for PaletteError {
+    fn from(e: std::io::Error) -> Self {
+        PaletteError::Io(e)
+    }
+}
+
+impl From<ColorParseError> for PaletteError {
+    fn from(e: ColorParseError) -> Self {
+        PaletteError::Color(e)
+    }
+}
+
+impl From<GradientError> for PaletteError {
+    fn from(e: GradientError) -> Self {
+        PaletteError::Gradient(e)
+    }
+}
+
+/// Gradient from a `--palette` value: a path to a `.ggr`, `.map`, `.json` or `.toml` palette
+/// file, an inline JSON stop list, or a comma separated list of colors spread evenly along the
+/// gradient
+pub fn parse_palette(s: &str) -> Result<Gradient, PaletteError> {
+    if s.trim_start().starts_with('{') {
+        return parse_json(s);
+    }
+    let path = Path::new(s);
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("ggr" | "map" | "json" | "toml") => load(path),
+        _ if path.is_file() => load(path),
+        _ => {
+            let colors = s
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<Vec<RGB>, _>>()?;
+            Ok(Gradient::evenly_spaced(&colors)?)
+        }
+    }
+}
+
+/// Gradient stored in a palette file, the format is chosen from its extension
+pub fn load(path: &Path) -> Result<Gradient, PaletteError> {
+    let contents = std::fs::read_to_string(path)?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension.to_ascii_lowercase().as_str() {
+        "ggr" => parse_ggr(&contents),
+        "json" => parse_json(&contents),
+        "toml" => parse_toml(&contents),
+        _ => parse_map(&contents),
+    }
+}
+
+/// Fractint `.map` palette: one `r g b` line with values in `[0, 255]` per color. Anything
+/// after the third value of a line is a comment.
+pub fn parse_map(contents: &str) -> Result<Gradient, PaletteError> {
+    let mut colors = vec![];
+    for (i, line) in contents.lines().enumerate() {
+        let mut values = line.split_whitespace();
+        let Some(first) = values.next() else {
+            continue;
+        };
+        let channel = |v: Option<&str>| {
+            v.and_then(|v| v.parse::<u8>().ok())
+                .ok_or_else(|| PaletteError::Syntax {
+                    line: i + 1,
+                    message: format!("expected three values in [0, 255], found {:?}", line),
+                })
+        };
+        colors.push(RGB {
+            r: channel(Some(first))?,
+            g: channel(values.next())?,
+            b: channel(values.next())?,
+        });
+    }
+    Ok(Gradient::evenly_spaced(&colors)?)
+}
+
+/// GIMP `.ggr` gradient. Each segment becomes stops at its left end, middle point and right
+/// end, so every blending function is approximated by a linear one.
+pub fn parse_ggr(contents: &str) -> Result<Gradient, PaletteError> {
+    let syntax = |line: usize, message: &str| PaletteError::Syntax {
+        line,
+        message: message.to_string(),
+    };
+
+    let mut lines = contents.lines().enumerate();
+    match lines.next() {
+        Some((_, header)) if header.trim() == "GIMP Gradient" => {}
+        _ => return Err(syntax(1, "missing \"GIMP Gradient\" header")),
+    }
+    let (i, mut line) = lines.next().ok_or_else(|| syntax(2, "missing segments"))?;
+    let mut line_number = i + 1;
+    if line.starts_with("Name:") {
+        let (i, next) = lines.next().ok_or_else(|| syntax(3, "missing segments"))?;
+        line_number = i + 1;
+        line = next;
+    }
+    let n_segments: usize = line
+        .trim()
+        .parse()
+        .map_err(|_| syntax(line_number, "expected the number of segments"))?;
+
+    let mut stops = vec![];
+    for _ in 0..n_segments {
+        let (i, line) = lines
+            .next()
+            .ok_or_else(|| syntax(line_number + 1, "missing segment"))?;
+        line_number = i + 1;
+        let values = line
+            .split_whitespace()
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<f64>, _>>()
+            .map_err(|_| syntax(line_number, "invalid number in segment"))?;
+        if values.len() < 11 {
+            return Err(syntax(line_number, "segment needs at least 11 values"));
+        }
+        let channel = |v: f64| (v.clamp(0., 1.) * 255.).round() as u8;
+        let left = RGB {
+            r: channel(values[3]),
+            g: channel(values[4]),
+            b: channel(values[5]),
+        };
+        let right = RGB {
+            r: channel(values[7]),
+            g: channel(values[8]),
+            b: channel(values[9]),
+        };
+        stops.push(ColorStop {
+            position: values[0],
+            color: left,
+        });
+        stops.push(ColorStop {
+            position: values[1],
+            color: left.lerp(right, 0.5),
+        });
+        stops.push(ColorStop {
+            position: values[2],
+            color: right,
+        });
+    }
+    Ok(Gradient::new(stops)?)
+}
+
+#[derive(Deserialize, Serialize)]
+struct StopList {
+    stops: Vec<StopEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct StopEntry {
+    position: f64,
+    color: String,
+}
+
+impl StopList {
+    fn into_gradient(self) -> Result<Gradient, PaletteError> {
+        let stops = self
+            .stops
+            .into_iter()
+            .map(|s| {
+                Ok(ColorStop {
+                    position: s.position,
+                    color: s.color.parse()?,
+                })
+            })
+            .collect::<Result<Vec<ColorStop>, PaletteError>>()?;
+        Ok(Gradient::new(stops)?)
+    }
+}
+
+/// JSON stop list: `{"stops": [{"position": 0.0, "color": "#E810DD"}, ...]}`
+pub fn parse_json(contents: &str) -> Result<Gradient, PaletteError> {
+    serde_json::from_str::<StopList>(contents)
+        .map_err(PaletteError::Json)?
+        .into_gradient()
+}
+
+/// TOML stop list, with one `[[stops]]` table holding a `position` and a `color` per stop
+pub fn parse_toml(contents: &str) -> Result<Gradient, PaletteError> {
+    toml::from_str::<StopList>(contents)
+        .map_err(PaletteError::Toml)?
+        .into_gradient()
+}
+
+/// JSON stop list of `gradient`, in the format read by `parse_json`
+pub fn to_json(gradient: &Gradient) -> String {
+    let stops = gradient
+        .stops()
+        .iter()
+        .map(|s| StopEntry {
+            position: s.position,
+            color: s.color.to_string(),
+        })
+        .collect();
+    serde_json::to_string(&StopList { stops }).unwrap()
+}
@@ -0,0 +1,242 @@
+use std::ops::Add;
+
+use num::complex::Complex;
+use num::Zero;
+
+use crate::floatexp::{self, ComplexExp, FloatExp};
+use crate::fractal::{Fractal, Orbit, Viewport};
+use crate::precision;
+
+/// Fractional bits of the reference orbit of the views that do not need fixed point for their
+/// pixels, a little more than f64 holds
+const REFERENCE_BITS: u32 = 64;
+
+/// Pauldelbrot's glitch criterion: once `|z|` falls under this fraction of `|Z|`, the value of
+/// the reference orbit it is computed from, the delta has lost most of its precision
+const GLITCH_TOLERANCE: f64 = 1e-3;
+
+/// Powers of `dc` kept by the series approximation
+const SERIES_TERMS: usize = 4;
+
+/// Largest estimated error of the series approximation, in pixels. Escape counts near the
+/// boundary change with a thousandth of a pixel, so the error is kept near the rounding of the
+/// deltas.
+const SERIES_TOLERANCE: f64 = 1e-9;
+
+/// Orbit of the view center computed once in fixed point and rounded to f64, which the orbits
+/// of the pixels follow as small differences `dz = z - Z` iterated in f64, or in `FloatExp`
+/// when the pixels are too small for f64
+pub struct Reference {
+    orbit: Vec<Complex<f64>>,
+    /// Coefficients of `dc`, `dc^2`, ... in the delta at iteration `skipped`
+    series: Vec<ComplexExp>,
+    skipped: usize,
+    series_error: f64,
+    bailout: f64,
+    floatexp: bool,
+}
+
+impl Reference {
+    /// Orbit of the center of `viewport` until it escapes the circle of radius `bailout` or
+    /// reaches `max_iter` iterations
+    pub fn new<F: Fractal + ?Sized>(
+        fractal: &F,
+        viewport: &Viewport,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Reference {
+        let c = viewport.precise_center.clone().unwrap_or_else(|| {
+            let center = Complex::new(viewport.center_x, viewport.center_y);
+            precision::complex_from_f64(center, REFERENCE_BITS)
+        });
+        let mut z = fractal.init_precise(&c);
+        let mut orbit = vec![precision::complex_to_f64(&z)];
+        for _ in 0..max_iter {
+            z = fractal.step_precise(&z, &c);
+            let rounded = precision::complex_to_f64(&z);
+            orbit.push(rounded);
+            if fractal.escaped(rounded, bailout) {
+                break;
+            }
+        }
+        // `init` is either zero or the pixel coordinate, so the delta starts as `init(1) dc`
+        let mut series = vec![Complex::zero(); SERIES_TERMS];
+        series[0] = floatexp::complex_from_f64(fractal.init(Complex::new(1., 0.)));
+        Reference {
+            orbit,
+            series,
+            skipped: 0,
+            series_error: 0.,
+            bailout,
+            floatexp: viewport.needs_floatexp(),
+        }
+    }
+
+    /// Same reference with the series approximation: the delta of every pixel is a polynomial
+    /// in `dc` whose coefficients only depend on the reference orbit, so they are iterated once
+    /// here and the pixels start at the last iteration where the polynomial is still accurate
+    /// over the whole `viewport`.
+    ///
+    /// The error of the truncated polynomial is estimated by the first term left out, and
+    /// compared to the distance between the deltas of neighbor pixels. The skipped iterations
+    /// must also keep every pixel inside the bailout radius, since their escape is not checked.
+    pub fn with_series<F: Fractal + ?Sized>(
+        mut self,
+        fractal: &F,
+        viewport: &Viewport,
+    ) -> Reference {
+        let (w, h) = (viewport.width as f64, viewport.height as f64);
+        // Samples lie at most one pixel past the image
+        let corners = [(-1., -1.), (w + 1., h + 1.)].map(|(x, y)| viewport.point_offset_exp(x, y));
+        let [first, last] = corners.map(floatexp::norm);
+        let radius = if first > last { first } else { last };
+        let pixel_size = viewport.pixel_size();
+        let mut series = self.series.clone();
+        series.push(Complex::zero());
+        // Pixels start one iteration before the end of the reference at the latest, so they
+        // have a step left to take
+        for m in 0..self.iterations().saturating_sub(1) {
+            let Some(next) = fractal.step_series(self.orbit[m], &series) else {
+                break;
+            };
+            let dropped = floatexp::norm(next[SERIES_TERMS]) * radius.powi(SERIES_TERMS as i32 + 1);
+            let error = (dropped / (floatexp::norm(next[0]) * pixel_size)).to_f64();
+            let largest_delta = (1..)
+                .zip(&next)
+                .map(|(k, &a)| floatexp::norm(a) * radius.powi(k))
+                .fold(FloatExp::zero(), |sum, term| sum + term)
+                .to_f64();
+            let valid = error <= SERIES_TOLERANCE
+                && largest_delta + self.orbit[m + 1].norm() <= self.bailout;
+            if !valid {
+                break;
+            }
+            series = next;
+            self.skipped = m + 1;
+            self.series_error = error;
+        }
+        series.truncate(SERIES_TERMS);
+        self.series = series;
+        self
+    }
+
+    /// Iterations of the reference orbit before it escaped or reached the max iterations
+    pub fn iterations(&self) -> usize {
+        self.orbit.len() - 1
+    }
+
+    /// Iterations every pixel skips thanks to the series approximation
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Estimated error of the series approximation at the skipped iteration, in pixels
+    pub fn series_error(&self) -> f64 {
+        self.series_error
+    }
+
+    /// Same as `fractal::iterate` for the point at `dc` from the center, without cycle
+    /// detection. The orbit starts from the series approximation.
+    ///
+    /// When the orbit reaches the end of the reference or glitches, it is rebased: its delta is
+    /// taken again from the start of the reference orbit, which is valid because every pixel
+    /// iterates the same map. A glitched orbit passes close to zero, where the start of the
+    /// reference is, so the new delta is small again.
+    pub fn iterate<F: Fractal + ?Sized>(
+        &self,
+        fractal: &F,
+        dc: ComplexExp,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Orbit {
+        let dz = self
+            .series
+            .iter()
+            .rev()
+            .fold(Complex::zero(), |sum, &a| (sum + a) * dc);
+        if self.floatexp {
+            self.iterate_deltas(fractal, dz, dc, max_iter, bailout)
+        } else {
+            let (dz, dc) = (floatexp::complex_to_f64(dz), floatexp::complex_to_f64(dc));
+            self.iterate_deltas(fractal, dz, dc, max_iter, bailout)
+        }
+    }
+
+    /// Rest of `iterate` from the first delta `dz`, with deltas of type `D`
+    fn iterate_deltas<F: Fractal + ?Sized, D: Delta>(
+        &self,
+        fractal: &F,
+        mut dz: D,
+        dc: D,
+        max_iter: u32,
+        bailout: f64,
+    ) -> Orbit {
+        let mut m = self.skipped;
+        for n in self.skipped as u32 + 1..=max_iter {
+            dz = D::step(fractal, self.orbit[m], dz, dc);
+            m += 1;
+            let z = self.orbit[m] + dz.to_f64();
+
+            if n >= max_iter {
+                break;
+            }
+            if fractal.escaped(z, bailout) {
+                return Orbit::Escaped { n, z };
+            }
+            let glitched =
+                z.norm_sqr() < GLITCH_TOLERANCE * GLITCH_TOLERANCE * self.orbit[m].norm_sqr();
+            if glitched || m == self.iterations() {
+                dz = D::from_f64(z - self.orbit[0]);
+                m = 0;
+            }
+        }
+        Orbit::Bounded { period: None }
+    }
+}
+
+/// Complex number type the deltas of the pixels are iterated in
+trait Delta: Copy + Add<Output = Self> {
+    fn step<F: Fractal + ?Sized>(fractal: &F, z: Complex<f64>, dz: Self, dc: Self) -> Self;
+    fn to_f64(self) -> Complex<f64>;
+    fn from_f64(z: Complex<f64>) -> Self;
+}
+
+impl Delta for Complex<f64> {
+    fn step<F: Fractal + ?Sized>(fractal: &F, z: Complex<f64>, dz: Self, dc: Self) -> Self {
+        fractal
+            .step_delta(z, dz, dc)
+            .expect("perturbation of a fractal without step_delta")
+    }
+
+    fn to_f64(self) -> Complex<f64> {
+        self
+    }
+
+    fn from_f64(z: Complex<f64>) -> Self {
+        z
+    }
+}
+
+impl Delta for ComplexExp {
+    fn step<F: Fractal + ?Sized>(fractal: &F, z: Complex<f64>, dz: Self, dc: Self) -> Self {
+        fractal
+            .step_delta_exp(z, dz, dc)
+            .expect("perturbation of a fractal without step_delta_exp")
+    }
+
+    fn to_f64(self) -> Complex<f64> {
+        floatexp::complex_to_f64(self)
+    }
+
+    fn from_f64(z: Complex<f64>) -> Self {
+        floatexp::complex_from_f64(z)
+    }
+}
+
+/// Product of two polynomials in `dc` without constant term, given by their coefficients of
+/// `dc`, `dc^2`, ... and truncated to the length of `a`
+pub fn series_product(a: &[ComplexExp], b: &[ComplexExp]) -> Vec<ComplexExp> {
+    (0..a.len())
+        .map(|k| (0..k).map(|i| a[i] * b[k - 1 - i]).sum())
+        .collect()
+}
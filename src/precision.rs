@@ -0,0 +1,265 @@
+use std::ops::{Add, Mul, Neg, Sub};
+use std::str::FromStr;
+
+use num::bigint::{BigInt, Sign};
+use num::complex::Complex;
+use num::traits::{Float, Pow, Signed, ToPrimitive, Zero};
+
+use crate::floatexp::{ComplexExp, FloatExp};
+
+/// Largest decimal exponent accepted by the parser, in absolute value. It is far past any zoom
+/// that can be rendered and keeps `Decimal::to_fixed` from computing huge powers of 10.
+const MAX_EXPONENT: i32 = 100_000;
+
+/// Exact decimal number `digits * 10^exponent`, used for the center of deep zooms which
+/// needs more digits than a f64 holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimal {
+    digits: BigInt,
+    exponent: i32,
+}
+
+/// Error returned when a string is not a decimal number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDecimalError(String);
+
+impl std::fmt::Display for ParseDecimalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid decimal number {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseDecimalError {}
+
+impl FromStr for Decimal {
+    type Err = ParseDecimalError;
+
+    /// Parse numbers like `-0.75`, `.5`, `+12` or `1.5e-20`, with any number of digits and an
+    /// exponent up to `MAX_EXPONENT` in absolute value
+    fn from_str(s: &str) -> Result<Decimal, ParseDecimalError> {
+        let error = || ParseDecimalError(s.to_string());
+        let (mantissa, exponent) = match s.find(['e', 'E']) {
+            Some(i) => (&s[..i], s[i + 1..].parse::<i32>().map_err(|_| error())?),
+            None => (s, 0),
+        };
+        if !(-MAX_EXPONENT..=MAX_EXPONENT).contains(&exponent) {
+            return Err(error());
+        }
+        let (sign, unsigned) = match mantissa.strip_prefix('-') {
+            Some(rest) => (Sign::Minus, rest),
+            None => (Sign::Plus, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+        };
+        let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if int.len() + frac.len() == 0 || !all_digits(int) || !all_digits(frac) {
+            return Err(error());
+        }
+        let digits: Vec<u8> = int.bytes().chain(frac.bytes()).map(|b| b - b'0').collect();
+        Ok(Decimal {
+            digits: BigInt::from_radix_be(sign, &digits, 10).ok_or_else(error)?,
+            exponent: exponent - frac.len() as i32,
+        })
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    /// Plain notation without exponent, which parses back to the same number
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.digits.is_negative() { "-" } else { "" };
+        let digits = self.digits.abs().to_string();
+        if self.exponent >= 0 {
+            let zeros = if self.digits.is_zero() {
+                0
+            } else {
+                self.exponent as usize
+            };
+            return write!(f, "{}{}{}", sign, digits, "0".repeat(zeros));
+        }
+        let frac_len = (-self.exponent) as usize;
+        let zeros = (frac_len + 1).saturating_sub(digits.len());
+        let digits = "0".repeat(zeros) + &digits;
+        let (int, frac) = digits.split_at(digits.len() - frac_len);
+        write!(f, "{}{}.{}", sign, int, frac)
+    }
+}
+
+impl From<f64> for Decimal {
+    /// Shortest decimal that rounds back to `x`
+    fn from(x: f64) -> Decimal {
+        format!("{:e}", x).parse().unwrap()
+    }
+}
+
+impl Decimal {
+    /// Nearest f64
+    pub fn to_f64(&self) -> f64 {
+        format!("{}e{}", self.digits, self.exponent)
+            .parse()
+            .unwrap()
+    }
+
+    /// Fixed-point value with `bits` fractional bits, rounded down
+    pub fn to_fixed(&self, bits: u32) -> Fixed {
+        let scaled = &self.digits << bits as usize;
+        let mantissa = if self.exponent >= 0 {
+            scaled * BigInt::from(10).pow(self.exponent as u32)
+        } else {
+            let divisor = BigInt::from(10).pow(self.exponent.unsigned_abs());
+            num::Integer::div_floor(&scaled, &divisor)
+        };
+        Fixed { mantissa, bits }
+    }
+}
+
+/// Arbitrary-precision fixed-point number `mantissa / 2^bits`. Operations keep the number of
+/// fractional bits of their operands, which must be the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixed {
+    mantissa: BigInt,
+    bits: u32,
+}
+
+impl Fixed {
+    pub fn zero(bits: u32) -> Fixed {
+        Fixed {
+            mantissa: BigInt::zero(),
+            bits,
+        }
+    }
+
+    /// Exact value of `x` with `bits` fractional bits, rounded down when `x` has more
+    pub fn from_f64(x: f64, bits: u32) -> Fixed {
+        Fixed::from_floatexp(x.into(), bits)
+    }
+
+    /// Same as `from_f64` for a `FloatExp`
+    pub fn from_floatexp(x: FloatExp, bits: u32) -> Fixed {
+        let (mantissa, exponent, sign) = x.mantissa().integer_decode();
+        let mantissa = BigInt::from(sign) * BigInt::from(mantissa);
+        let shift = exponent as i64 + x.exponent() + bits as i64;
+        let mantissa = if shift >= 0 {
+            mantissa << shift as usize
+        } else {
+            mantissa >> (-shift) as usize
+        };
+        Fixed { mantissa, bits }
+    }
+
+    /// Nearest f64, up to the rounding of the bits past the 64 highest ones
+    pub fn to_f64(&self) -> f64 {
+        self.to_floatexp().to_f64()
+    }
+
+    /// Same as `to_f64` without the limits of the f64 exponent
+    pub fn to_floatexp(&self) -> FloatExp {
+        let drop = self.mantissa.bits().saturating_sub(64);
+        let top = (&self.mantissa >> drop as usize).to_f64().unwrap_or(0.);
+        FloatExp::new(top, drop as i64 - self.bits as i64)
+    }
+
+    pub fn abs(&self) -> Fixed {
+        Fixed {
+            mantissa: self.mantissa.abs(),
+            bits: self.bits,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+}
+
+impl Add for &Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: &Fixed) -> Fixed {
+        debug_assert_eq!(self.bits, other.bits);
+        Fixed {
+            mantissa: &self.mantissa + &other.mantissa,
+            bits: self.bits,
+        }
+    }
+}
+
+impl Sub for &Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: &Fixed) -> Fixed {
+        debug_assert_eq!(self.bits, other.bits);
+        Fixed {
+            mantissa: &self.mantissa - &other.mantissa,
+            bits: self.bits,
+        }
+    }
+}
+
+impl Mul for &Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: &Fixed) -> Fixed {
+        debug_assert_eq!(self.bits, other.bits);
+        Fixed {
+            mantissa: (&self.mantissa * &other.mantissa) >> self.bits as usize,
+            bits: self.bits,
+        }
+    }
+}
+
+impl Neg for &Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed {
+            mantissa: -&self.mantissa,
+            bits: self.bits,
+        }
+    }
+}
+
+/// Complex number in fixed point. The arithmetic of `num::Complex` needs numeric traits that
+/// `Fixed` does not implement, so the operations used by the fractals are written here.
+pub type ComplexFixed = Complex<Fixed>;
+
+pub fn complex_add(a: &ComplexFixed, b: &ComplexFixed) -> ComplexFixed {
+    Complex::new(&a.re + &b.re, &a.im + &b.im)
+}
+
+pub fn complex_sub(a: &ComplexFixed, b: &ComplexFixed) -> ComplexFixed {
+    Complex::new(&a.re - &b.re, &a.im - &b.im)
+}
+
+pub fn complex_mul(a: &ComplexFixed, b: &ComplexFixed) -> ComplexFixed {
+    Complex::new(
+        &(&a.re * &b.re) - &(&a.im * &b.im),
+        &(&a.re * &b.im) + &(&a.im * &b.re),
+    )
+}
+
+/// `z * z`, with one multiplication less than `complex_mul`
+pub fn complex_square(z: &ComplexFixed) -> ComplexFixed {
+    let re_im = &z.re * &z.im;
+    Complex::new(&(&z.re * &z.re) - &(&z.im * &z.im), &re_im + &re_im)
+}
+
+/// Nearest f64 complex number
+pub fn complex_to_f64(z: &ComplexFixed) -> Complex<f64> {
+    Complex::new(z.re.to_f64(), z.im.to_f64())
+}
+
+/// `z` with `bits` fractional bits
+pub fn complex_from_f64(z: Complex<f64>, bits: u32) -> ComplexFixed {
+    Complex::new(Fixed::from_f64(z.re, bits), Fixed::from_f64(z.im, bits))
+}
+
+/// Nearest `FloatExp` complex number
+pub fn complex_to_floatexp(z: &ComplexFixed) -> ComplexExp {
+    Complex::new(z.re.to_floatexp(), z.im.to_floatexp())
+}
+
+/// `z` with `bits` fractional bits
+pub fn complex_from_floatexp(z: ComplexExp, bits: u32) -> ComplexFixed {
+    Complex::new(
+        Fixed::from_floatexp(z.re, bits),
+        Fixed::from_floatexp(z.im, bits),
+    )
+}
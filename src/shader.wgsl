@@ -11,10 +11,24 @@ struct VertexOutput {
 };
 
 struct FractalInfo {
+    // Center in double-single, pos_x + pos_x_lo and pos_y + pos_y_lo
     pos_x: f32,
     pos_y: f32,
+    pos_x_lo: f32,
+    pos_y_lo: f32,
     scale: f32,
-    img_size_w: f32,
+    max_iter: f32,
+    fractal_type: u32,
+    power: u32,
+    julia_re: f32,
+    julia_im: f32,
+    bailout: f32,
+    coloring: u32,
+    cycle_length: f32,
+    cycle_offset: f32,
+    interior_check: u32,
+    // Always 0, see opaque
+    zero: u32,
 };
 @group(0) @binding(0) 
 var<uniform> fractalInfo: FractalInfo;
@@ -22,12 +36,18 @@ var<uniform> fractalInfo: FractalInfo;
 struct ScreenInfo {
     img_size_w: f32,
     img_size_h: f32,
+    samples: u32,
     pad: f32,
-    pad2: f32,
 };
 @group(1) @binding(0) 
 var<uniform> screenInfo: ScreenInfo;
 
+// Palette uploaded from algcolor, as a single row texture
+@group(2) @binding(0)
+var palette_texture: texture_2d<f32>;
+@group(2) @binding(1)
+var palette_sampler: sampler;
+
 @vertex
 fn vs_main(model: VertexInput) -> VertexOutput {
     var out: VertexOutput;
@@ -41,35 +61,302 @@ fn complex_mul(c1: vec2<f32>, c2: vec2<f32>) -> vec2<f32>{
     return vec2<f32>(c1[0]*c2[0]-c1[1]*c2[1], c1[0]*c2[1] + c1[1]*c2[0]);
 }
 
-fn mandelbrot_divergence(c: vec2<f32>) -> f32 {
-    let max_iter = 500.0;
-    var z = vec2<f32>(0.0,0.0);
+fn complex_pow(z: vec2<f32>, power: u32) -> vec2<f32> {
+    var result = vec2<f32>(1.0, 0.0);
+    for (var i = 0u; i < power; i++) {
+        result = complex_mul(result, z);
+    }
+    return result;
+}
+
+// Double-single arithmetic: a number is the unevaluated sum hi + lo of two f32 in a vec2, with
+// about 48 bits of mantissa, and a complex number is a vec4 (re.hi, re.lo, im.hi, im.lo).
+// The error-free transformations below rely on every f32 operation being rounded as written.
+
+// x unchanged, but unknown to the shader compiler. Drivers simplify (a + b) - a into b and
+// reorder sums, which removes the rounding errors these transformations compute. The
+// compiler cannot see through integer operations with a uniform, unlike a product with 1.
+fn opaque(x: f32) -> f32 {
+    return bitcast<f32>(bitcast<u32>(x) ^ fractalInfo.zero);
+}
+
+// a + b as the rounded sum and its rounding error (Knuth)
+fn two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = opaque(a + b);
+    let v = opaque(s - a);
+    return vec2<f32>(s, opaque(a - opaque(s - v)) + opaque(b - v));
+}
+
+// Same as two_sum when |a| >= |b|
+fn quick_two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = opaque(a + b);
+    return vec2<f32>(s, b - opaque(s - a));
+}
+
+// a as the sum of two halves of 12 bits each (Dekker)
+fn split(a: f32) -> vec2<f32> {
+    let t = opaque(4097.0 * a);
+    let hi = t - opaque(t - a);
+    return vec2<f32>(hi, a - hi);
+}
+
+// a * b as the rounded product and its rounding error
+fn two_prod(a: f32, b: f32) -> vec2<f32> {
+    let p = a * b;
+    let x = split(a);
+    let y = split(b);
+    return vec2<f32>(p, ((x.x * y.x - p) + x.x * y.y + x.y * y.x) + x.y * y.y);
+}
+
+fn ds_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = two_sum(a.x, b.x);
+    let t = two_sum(a.y, b.y);
+    let u = quick_two_sum(s.x, s.y + t.x);
+    return quick_two_sum(u.x, u.y + t.y);
+}
+
+fn ds_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = two_prod(a.x, b.x);
+    return quick_two_sum(p.x, p.y + (a.x * b.y + a.y * b.x));
+}
+
+fn ds_abs(a: vec2<f32>) -> vec2<f32> {
+    return select(a, -a, a.x < 0.0);
+}
+
+fn cds_add(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(ds_add(a.xy, b.xy), ds_add(a.zw, b.zw));
+}
+
+fn cds_mul(a: vec4<f32>, b: vec4<f32>) -> vec4<f32> {
+    let re = ds_add(ds_mul(a.xy, b.xy), -ds_mul(a.zw, b.zw));
+    let im = ds_add(ds_mul(a.xy, b.zw), ds_mul(a.zw, b.xy));
+    return vec4<f32>(re, im);
+}
+
+fn cds_pow(z: vec4<f32>, power: u32) -> vec4<f32> {
+    var result = vec4<f32>(1.0, 0.0, 0.0, 0.0);
+    for (var i = 0u; i < power; i++) {
+        result = cds_mul(result, z);
+    }
+    return result;
+}
+
+// Rounded to f32
+fn cds_hi(z: vec4<f32>) -> vec2<f32> {
+    return z.xz;
+}
+
+// fractal_type follows the order of fractal::FractalKind:
+// 0 mandelbrot, 1 julia, 2 burning ship, 3 tricorn, 4 multibrot
+fn fractal_init(c: vec2<f32>) -> vec2<f32> {
+    if fractalInfo.fractal_type == 1u {
+        return c;
+    }
+    return vec2<f32>(0.0, 0.0);
+}
+
+fn fractal_step(z: vec2<f32>, c: vec2<f32>) -> vec2<f32> {
+    switch fractalInfo.fractal_type {
+        case 1u: {
+            return complex_mul(z, z) + vec2<f32>(fractalInfo.julia_re, fractalInfo.julia_im);
+        }
+        case 2u: {
+            let a = abs(z);
+            return complex_mul(a, a) + c;
+        }
+        case 3u: {
+            let conj = vec2<f32>(z[0], -z[1]);
+            return complex_mul(conj, conj) + c;
+        }
+        case 4u: {
+            return complex_pow(z, fractalInfo.power) + c;
+        }
+        default: {
+            return complex_mul(z, z) + c;
+        }
+    }
+}
+
+// Same as fractal_init and fractal_step in double-single
+fn fractal_init_ds(c: vec4<f32>) -> vec4<f32> {
+    if fractalInfo.fractal_type == 1u {
+        return c;
+    }
+    return vec4<f32>(0.0);
+}
+
+fn fractal_step_ds(z: vec4<f32>, c: vec4<f32>) -> vec4<f32> {
+    switch fractalInfo.fractal_type {
+        case 1u: {
+            let julia = vec4<f32>(fractalInfo.julia_re, 0.0, fractalInfo.julia_im, 0.0);
+            return cds_add(cds_mul(z, z), julia);
+        }
+        case 2u: {
+            let a = vec4<f32>(ds_abs(z.xy), ds_abs(z.zw));
+            return cds_add(cds_mul(a, a), c);
+        }
+        case 3u: {
+            let conj = vec4<f32>(z.xy, -z.zw);
+            return cds_add(cds_mul(conj, conj), c);
+        }
+        case 4u: {
+            return cds_add(cds_pow(z, fractalInfo.power), c);
+        }
+        default: {
+            return cds_add(cds_mul(z, z), c);
+        }
+    }
+}
+
+fn fractal_degree() -> f32 {
+    if fractalInfo.fractal_type == 4u {
+        return f32(fractalInfo.power);
+    }
+    return 2.0;
+}
+
+// Same test as fractal::cardioid_or_bulb_period, without the period
+fn in_cardioid_or_bulb(c: vec2<f32>) -> bool {
+    let x = c.x - 0.25;
+    let y2 = c.y * c.y;
+    let q = x * x + y2;
+    let b = c.x + 1.0;
+    return q * (q + x) <= 0.25 * y2 || b * b + y2 <= 0.0625;
+}
+
+// Whether c is known to be in the set without iterating, only for z^2 + c from zero
+fn known_interior(c: vec2<f32>) -> bool {
+    let z2 = fractalInfo.fractal_type == 0u || (fractalInfo.fractal_type == 4u && fractalInfo.power == 2u);
+    return fractalInfo.interior_check != 0u && z2 && in_cardioid_or_bulb(c);
+}
+
+// Pixels closer than this many f32 steps of the center are iterated in double-single, same
+// role as fractal::MIN_ULPS_PER_PIXEL
+const MIN_ULPS_PER_PIXEL: f32 = 16.0;
+
+// Last value of an orbit and its number of iterations, n is max_iter when it did not escape
+struct Escape {
+    z: vec2<f32>,
+    n: f32,
+};
+
+fn escape(c: vec2<f32>) -> Escape {
+    var z = fractal_init(c);
     var n = 0.0;
     loop {
-        z = complex_mul(z, z) + c;
+        z = fractal_step(z, c);
         n += 1.0;
+        if n >= fractalInfo.max_iter || length(z) > fractalInfo.bailout {
+            return Escape(z, n);
+        }
+    }
+    return Escape(z, n);
+}
 
-        if length(z) >= 2.0 || n >= max_iter {
-            break;
+fn escape_ds(c: vec4<f32>) -> Escape {
+    var z = fractal_init_ds(c);
+    var n = 0.0;
+    loop {
+        z = fractal_step_ds(z, c);
+        n += 1.0;
+        if n >= fractalInfo.max_iter || length(cds_hi(z)) > fractalInfo.bailout {
+            return Escape(cds_hi(z), n);
         }
     }
-    return n/(max_iter);
+    return Escape(cds_hi(z), n);
 }
 
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let scale = fractalInfo.scale;
-    let min_x = fractalInfo.pos_x + (-2. / scale);
-    let max_x = fractalInfo.pos_x + (2. / scale);
-    let min_y = fractalInfo.pos_y + (-2. / scale);
-    let max_y = fractalInfo.pos_y + (2. / scale);
+// Whether the spacing of f32 values around the center is too coarse for the pixel size, the
+// same test as fractal::Viewport::precision_bits
+fn needs_double_single() -> bool {
+    let pixel_size = 4.0 / (fractalInfo.scale * min(screenInfo.img_size_w, screenInfo.img_size_h));
+    let magnitude = max(max(abs(fractalInfo.pos_x), abs(fractalInfo.pos_y)), 1.0);
+    // Machine epsilon of f32
+    return pixel_size < magnitude * 1.1920929e-7 * MIN_ULPS_PER_PIXEL;
+}
+
+// Smooth iteration count in [1, max_iter] of the point c in double-single, same formula as
+// fractal::smooth_escape_time
+fn mandelbrot_divergence(c: vec4<f32>) -> f32 {
+    let max_iter = fractalInfo.max_iter;
+    let bailout = fractalInfo.bailout;
+    if known_interior(cds_hi(c)) {
+        return max_iter;
+    }
+    var orbit: Escape;
+    if needs_double_single() {
+        orbit = escape_ds(c);
+    } else {
+        orbit = escape(cds_hi(c));
+    }
+    if orbit.n >= max_iter {
+        return max_iter;
+    }
+    let nu = log(log(length(orbit.z)) / log(bailout)) / log(fractal_degree());
+    return clamp(orbit.n + 1.0 - nu, 1.0, max_iter);
+}
 
-    let min_screen_size = min(screenInfo.img_size_w, screenInfo.img_size_h);
+// Color at the fraction t of the palette
+fn palette_sample(t: f32) -> vec4<f32> {
+    let size = f32(textureDimensions(palette_texture).x);
+    let u = (t * (size - 1.0) + 0.5) / size;
+    return textureSampleLevel(palette_texture, palette_sampler, vec2<f32>(u, 0.5), 0.0);
+}
+
+// Color for the smooth count n. The CPU renders index a palette of max_iter colors
+// with n - 1, so the same fraction of the palette is looked up here.
+// coloring follows coloring::Coloring: 0 linear, 1 histogram (see histogram.wgsl), 2 cyclic
+fn palette_color(n: f32) -> vec4<f32> {
+    if fractalInfo.coloring == 2u && n < fractalInfo.max_iter {
+        // Same as coloring::cyclic_position
+        let p = (n - 1.0) / fractalInfo.cycle_length + fractalInfo.cycle_offset;
+        let m = p - 2.0 * floor(p / 2.0);
+        return palette_sample(select(m, 2.0 - m, m > 1.0));
+    }
+    return palette_sample((n - 1.0) / max(fractalInfo.max_iter - 1.0, 1.0));
+}
 
-    let x = (in.clip_position.x / min_screen_size) * (max_x - min_x) + min_x;
-    let y = (in.clip_position.y / min_screen_size) * (max_y - min_y) + min_y;
+// Point of the complex plane under the pixel position in the screen, in double-single. The
+// center of the screen is the view center and the shorter side spans 4 / scale, same as
+// fractal::Viewport. The offset from the center is small enough for f32, only the sum needs
+// the extra precision.
+fn pixel_to_complex(pixel: vec2<f32>) -> vec4<f32> {
+    let screen_size = vec2<f32>(screenInfo.img_size_w, screenInfo.img_size_h);
+    let min_screen_size = min(screen_size.x, screen_size.y);
+    let offset = (pixel - screen_size / 2.0) * 4.0 / (fractalInfo.scale * min_screen_size);
+    let x = ds_add(vec2<f32>(fractalInfo.pos_x, fractalInfo.pos_x_lo), vec2<f32>(offset.x, 0.0));
+    let y = ds_add(vec2<f32>(fractalInfo.pos_y, fractalInfo.pos_y_lo), vec2<f32>(offset.y, 0.0));
+    return vec4<f32>(x, y);
+}
 
-    let n = mandelbrot_divergence(vec2<f32>(x,y));
+// Rotated grid sample i of a pixel with samples x samples of them, relative to its top left
+// corner. Same positions as supersampling::Pattern::Rotated.
+fn sample_offset(i: u32, samples: u32) -> vec2<f32> {
+    let col = i % samples;
+    let row = i / samples;
+    let nn = f32(samples * samples);
+    return vec2<f32>(
+        (f32(col * samples + row) + 0.5) / nn,
+        (f32(row * samples + samples - 1u - col) + 0.5) / nn,
+    );
+}
 
-    return vec4<f32>(0.0, n, 0.0, 1.0);
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let samples = screenInfo.samples;
+    if samples <= 1u {
+        let n = mandelbrot_divergence(pixel_to_complex(in.clip_position.xy));
+        return palette_color(n);
+    }
+
+    // Box filter over the samples. Palette colors are read from an sRGB texture, so they are
+    // already in linear light and can be averaged directly.
+    let corner = floor(in.clip_position.xy);
+    var color = vec4<f32>(0.0);
+    for (var i = 0u; i < samples * samples; i++) {
+        let n = mandelbrot_divergence(pixel_to_complex(corner + sample_offset(i, samples)));
+        color += palette_color(n);
+    }
+    return color / f32(samples * samples);
 }
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Side under which rectangles are computed pixel by pixel instead of being split again
+const MIN_SIZE: u32 = 6;
+
+/// Bits of a value not computed yet. Escape values are never NaN.
+const UNSET: u64 = u64::MAX;
+
+/// Escape values of a `width` x `height` grid in row-major order, computed with the
+/// Mariani-Silver rectangle subdivision: the border of a rectangle is computed first and, when
+/// every border pixel has the same value, the inside is filled with it without iterating. Other
+/// rectangles are split in two halves until they are small enough to be computed pixel by pixel.
+///
+/// `value(x, y)` is the value of a single pixel and only the values for which `fillable` holds
+/// are spread over a rectangle. The fill relies on the regions of equal value having no holes,
+/// which holds for the inside of connected sets like the Mandelbrot set but not for the bands
+/// of equal escape count around a set that is entirely in the rectangle. A rectangle with a
+/// uniform border of another value is split like the others, so every pixel of it is checked.
+pub fn subdivide(
+    (width, height): (u32, u32),
+    parallel: bool,
+    value: impl Fn(u32, u32) -> f64 + Sync,
+    fillable: impl Fn(f64) -> bool + Sync,
+) -> Vec<f64> {
+    if width == 0 || height == 0 {
+        return vec![];
+    }
+    let grid = Grid {
+        width,
+        values: (0..width * height).map(|_| AtomicU64::new(UNSET)).collect(),
+        value,
+        fillable,
+        parallel,
+    };
+    grid.rectangle(0, 0, width - 1, height - 1);
+    grid.values
+        .into_iter()
+        .map(|v| f64::from_bits(v.into_inner()))
+        .collect()
+}
+
+/// Values shared by the rectangles, each pixel being computed at most once per thread that
+/// reaches it first
+struct Grid<F, G> {
+    width: u32,
+    values: Vec<AtomicU64>,
+    value: F,
+    fillable: G,
+    parallel: bool,
+}
+
+impl<F: Fn(u32, u32) -> f64 + Sync, G: Fn(f64) -> bool + Sync> Grid<F, G> {
+    fn get(&self, x: u32, y: u32) -> f64 {
+        let cell = &self.values[(y * self.width + x) as usize];
+        match cell.load(Ordering::Relaxed) {
+            UNSET => {
+                let v = (self.value)(x, y);
+                cell.store(v.to_bits(), Ordering::Relaxed);
+                v
+            }
+            bits => f64::from_bits(bits),
+        }
+    }
+
+    fn set(&self, x: u32, y: u32, v: f64) {
+        self.values[(y * self.width + x) as usize].store(v.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Compute the rectangle of corners `(x0, y0)` and `(x1, y1)`, both included
+    fn rectangle(&self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        let top_bottom = (x0..=x1).flat_map(|x| [(x, y0), (x, y1)]);
+        let left_right = (y0..=y1).flat_map(|y| [(x0, y), (x1, y)]);
+        let first = self.get(x0, y0);
+        // Every border pixel is computed, even after a difference is found, since the halves
+        // share them
+        let uniform = top_bottom
+            .chain(left_right)
+            .fold(true, |uniform, (x, y)| self.get(x, y) == first && uniform);
+
+        if x1 - x0 < 2 || y1 - y0 < 2 {
+            return;
+        }
+        if uniform && (self.fillable)(first) {
+            for y in y0 + 1..y1 {
+                for x in x0 + 1..x1 {
+                    self.set(x, y, first);
+                }
+            }
+        } else if x1 - x0 < MIN_SIZE || y1 - y0 < MIN_SIZE {
+            for y in y0 + 1..y1 {
+                for x in x0 + 1..x1 {
+                    self.get(x, y);
+                }
+            }
+        } else {
+            let halves = if x1 - x0 >= y1 - y0 {
+                let xm = (x0 + x1) / 2;
+                [(x0, y0, xm, y1), (xm, y0, x1, y1)]
+            } else {
+                let ym = (y0 + y1) / 2;
+                [(x0, y0, x1, ym), (x0, ym, x1, y1)]
+            };
+            let [a, b] = halves.map(|(x0, y0, x1, y1)| move || self.rectangle(x0, y0, x1, y1));
+            if self.parallel {
+                rayon::join(a, b);
+            } else {
+                a();
+                b();
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::algcolor::{linear_to_srgb, srgb_to_linear};
+
+/// Placement of the samples inside a pixel
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Pattern {
+    /// Regular N x N grid
+    Grid,
+    /// One random point in each cell of the N x N grid
+    Jitter,
+    /// N x N grid rotated so that every row and column of the pixel holds a single sample,
+    /// which handles near horizontal and vertical edges better than the regular grid
+    Rotated,
+}
+
+/// Reconstruction filter weighting the samples into pixel colors
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum Filter {
+    /// Plain average of the samples of the pixel
+    Box,
+    /// Gaussian around the pixel center, also taking the samples of the neighbor pixels
+    Gaussian,
+}
+
+/// Standard deviation of the gaussian filter, in pixels
+const GAUSSIAN_SIGMA: f64 = 0.5;
+/// Samples further than this from the pixel center, in pixels, are ignored by the gaussian filter
+const GAUSSIAN_RADIUS: f64 = 1.5;
+
+/// Several escape-time samples per pixel, blended to remove the aliasing around filaments
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Supersampling {
+    /// Samples along each axis, a pixel gets `samples * samples` of them
+    pub samples: u32,
+    pub pattern: Pattern,
+    pub filter: Filter,
+    /// Only supersample the pixels on edges, where a neighbor color differs by more than this
+    /// threshold. See `edge_pixels`.
+    pub adaptive_threshold: Option<f64>,
+}
+
+impl Supersampling {
+    /// A single sample at the center of each pixel
+    pub const NONE: Supersampling = Supersampling {
+        samples: 1,
+        pattern: Pattern::Grid,
+        filter: Filter::Box,
+        adaptive_threshold: None,
+    };
+
+    /// Samples per pixel
+    pub fn count(&self) -> u32 {
+        self.samples * self.samples
+    }
+
+    /// Position of the sample `i` of the pixel `(x, y)`, relative to its top left corner and in
+    /// pixels. Jittered positions only depend on the pixel and the sample, so renders are
+    /// reproducible.
+    pub fn offset(&self, x: u32, y: u32, i: u32) -> (f64, f64) {
+        let n = self.samples;
+        // A single sample sits at the center of the pixel, where the patterns center their
+        // samples too
+        if n <= 1 {
+            return (0.5, 0.5);
+        }
+        let (col, row) = (i % n, i / n);
+        let nf = n as f64;
+        match self.pattern {
+            Pattern::Grid => ((col as f64 + 0.5) / nf, (row as f64 + 0.5) / nf),
+            Pattern::Jitter => {
+                let hash = splitmix64(((x as u64) << 32 | y as u64) ^ (i as u64).rotate_left(17));
+                let u = (hash >> 11) as f64 / (1u64 << 53) as f64;
+                let v = (splitmix64(hash) >> 11) as f64 / (1u64 << 53) as f64;
+                ((col as f64 + u) / nf, (row as f64 + v) / nf)
+            }
+            // The grid steps are (1/n, -1/n²) and (1/n², 1/n): orthogonal, equal length and
+            // periodic with the pixel
+            Pattern::Rotated => {
+                let nn = nf * nf;
+                (
+                    ((col * n + row) as f64 + 0.5) / nn,
+                    ((row * n + n - 1 - col) as f64 + 0.5) / nn,
+                )
+            }
+        }
+    }
+
+    /// Pixel colors of a `width` x `height` image from the colors of its samples, stored in
+    /// row-major pixel order with `count()` consecutive samples per pixel. Colors are sRGB with
+    /// channels in `[0, 1]` and are blended in linear light.
+    pub fn resolve(&self, colors: &[[f64; 3]], width: u32, height: u32) -> Vec<[f64; 3]> {
+        let count = self.count();
+        if count == 1 {
+            return colors.to_vec();
+        }
+        let linear: Vec<[f64; 3]> = colors.iter().map(|c| c.map(srgb_to_linear)).collect();
+        let sample = |x: u32, y: u32, i: u32| linear[((y * width + x) * count + i) as usize];
+
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = match self.filter {
+                    Filter::Box => blend((0..count).map(|i| (sample(x, y, i), 1.))),
+                    Filter::Gaussian => {
+                        let neighbors = (y.saturating_sub(1)..(y + 2).min(height)).flat_map(|ny| {
+                            (x.saturating_sub(1)..(x + 2).min(width)).map(move |nx| (nx, ny))
+                        });
+                        blend(neighbors.flat_map(|(nx, ny)| {
+                            (0..count).map(move |i| {
+                                let weight = self.gaussian_weight((x, y), (nx, ny), i);
+                                (sample(nx, ny, i), weight)
+                            })
+                        }))
+                    }
+                };
+                pixels.push(pixel);
+            }
+        }
+        pixels
+    }
+
+    /// Color of the pixel `(x, y)` from the colors of its own `count()` samples, used when only
+    /// some pixels are supersampled. The gaussian filter weighs them without the neighbor
+    /// pixels.
+    pub fn resolve_pixel(&self, colors: &[[f64; 3]], x: u32, y: u32) -> [f64; 3] {
+        blend(colors.iter().enumerate().map(|(i, c)| {
+            let weight = match self.filter {
+                Filter::Box => 1.,
+                Filter::Gaussian => self.gaussian_weight((x, y), (x, y), i as u32),
+            };
+            (c.map(srgb_to_linear), weight)
+        }))
+    }
+
+    /// Weight of the sample `i` of the pixel `sample_pixel` in the color of `pixel`
+    fn gaussian_weight(&self, pixel: (u32, u32), sample_pixel: (u32, u32), i: u32) -> f64 {
+        let gaussian = |d2: f64| (-d2 / (2. * GAUSSIAN_SIGMA * GAUSSIAN_SIGMA)).exp();
+        let (ox, oy) = self.offset(sample_pixel.0, sample_pixel.1, i);
+        let dx = sample_pixel.0 as f64 + ox - (pixel.0 as f64 + 0.5);
+        let dy = sample_pixel.1 as f64 + oy - (pixel.1 as f64 + 0.5);
+        (gaussian(dx * dx + dy * dy) - gaussian(GAUSSIAN_RADIUS * GAUSSIAN_RADIUS)).max(0.)
+    }
+}
+
+/// sRGB color of the weighted average of linear light colors
+fn blend(samples: impl Iterator<Item = ([f64; 3], f64)>) -> [f64; 3] {
+    let mut sum = [0.; 3];
+    let mut total = 0.;
+    for (color, weight) in samples {
+        for k in 0..3 {
+            sum[k] += color[k] * weight;
+        }
+        total += weight;
+    }
+    sum.map(|c| linear_to_srgb(c / total))
+}
+
+/// Pixels of a `width` x `height` image worth supersampling: the ones with a neighbor, among
+/// the 8 around them, on the other side of the set boundary or with a color that differs by
+/// more than `threshold` in some sRGB channel. `values` and `colors` hold one escape count and
+/// one color per pixel in row-major order.
+pub fn edge_pixels(
+    values: &[f64],
+    colors: &[[f64; 3]],
+    max_iter: u32,
+    (width, height): (u32, u32),
+    threshold: f64,
+) -> Vec<u32> {
+    let inside = |i: usize| values[i] >= max_iter as f64;
+    let differ = |i: usize, j: usize| {
+        inside(i) != inside(j) || (0..3).any(|k| (colors[i][k] - colors[j][k]).abs() > threshold)
+    };
+    (0..width * height)
+        .filter(|&p| {
+            let (x, y) = (p % width, p / width);
+            (y.saturating_sub(1)..(y + 2).min(height)).any(|ny| {
+                (x.saturating_sub(1)..(x + 2).min(width))
+                    .any(|nx| differ(p as usize, (ny * width + nx) as usize))
+            })
+        })
+        .collect()
+}
+
+/// SplitMix64 step, a small hash giving well distributed bits for the jittered samples
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
@@ -7,17 +7,22 @@ pub struct WBuffer {
 }
 
 impl WBuffer {
-    pub fn new_fragment_uniform(device: &wgpu::Device, contents: &[u8], label: &str) -> Self {
+    pub fn new_uniform(
+        device: &wgpu::Device,
+        contents: &[u8],
+        label: &str,
+        visibility: wgpu::ShaderStages,
+    ) -> Self {
         let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(&format!("{} Buffer", label)[..]),
-            contents: contents,
+            contents,
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[wgpu::BindGroupLayoutEntry {
                 binding: 0,
-                visibility: wgpu::ShaderStages::FRAGMENT,
+                visibility,
                 ty: wgpu::BindingType::Buffer {
                     ty: wgpu::BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -0,0 +1,205 @@
+use crate::wpipeline::WPipeline;
+
+/// Bins of the GPU histogram of escape counts, matching `HISTOGRAM_BINS` in histogram.wgsl
+const HISTOGRAM_BINS: u64 = 4096;
+
+/// Histogram coloring on the GPU: a compute pass stores the escape count of every pixel and
+/// builds their cumulative distribution, then a render pass colors the stored counts with it
+pub struct WHistogram {
+    escape_values_buffer: wgpu::Buffer,
+    histogram_buffer: wgpu::Buffer,
+    cumulative_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    escape_values_pipeline: wgpu::ComputePipeline,
+    cumulative_pipeline: wgpu::ComputePipeline,
+    pub render_pipeline: WPipeline,
+}
+
+impl WHistogram {
+    /// Whether the adapter can run the compute and storage passes
+    pub fn is_supported(adapter: &wgpu::Adapter) -> bool {
+        adapter.get_downlevel_capabilities().flags.contains(
+            wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::FRAGMENT_WRITABLE_STORAGE,
+        )
+    }
+
+    /// `bind_groups_layouts` are the layouts of the groups used by shader.wgsl, the histogram
+    /// buffers are bound to the next group
+    pub fn new(
+        device: &wgpu::Device,
+        bind_groups_layouts: &[&wgpu::BindGroupLayout],
+        texture_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> WHistogram {
+        let storage_entry =
+            |binding: u32, visibility: wgpu::ShaderStages| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                storage_entry(
+                    0,
+                    wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT,
+                ),
+                storage_entry(1, wgpu::ShaderStages::COMPUTE),
+                storage_entry(
+                    2,
+                    wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT,
+                ),
+            ],
+            label: Some("Histogram_bind_group_layout"),
+        });
+
+        let mut layouts = bind_groups_layouts.to_vec();
+        layouts.push(&bind_group_layout);
+        let shader_code = [include_str!("shader.wgsl"), include_str!("histogram.wgsl")].concat();
+
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Histogram: Compute Pipeline Layout"),
+                bind_group_layouts: &layouts,
+                push_constant_ranges: &[],
+            });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Histogram: Shader Code"),
+            source: wgpu::ShaderSource::Wgsl(shader_code.as_str().into()),
+        });
+        let compute_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("Histogram: {}", entry_point)[..]),
+                layout: Some(&compute_pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+        let escape_values_pipeline = compute_pipeline("cs_escape_values");
+        let cumulative_pipeline = compute_pipeline("cs_cumulative");
+
+        let render_pipeline = WPipeline::new_render_pipeline(
+            device,
+            &layouts,
+            shader_code.into(),
+            "fs_histogram",
+            texture_format,
+            "Histogram",
+        );
+
+        let histogram_buffer = Self::create_buffer(device, HISTOGRAM_BINS * 4, "Histogram Buffer");
+        let cumulative_buffer =
+            Self::create_buffer(device, HISTOGRAM_BINS * 4, "Histogram Cumulative Buffer");
+        let escape_values_buffer = Self::create_escape_values_buffer(device, width, height);
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &escape_values_buffer,
+            &histogram_buffer,
+            &cumulative_buffer,
+        );
+
+        WHistogram {
+            escape_values_buffer,
+            histogram_buffer,
+            cumulative_buffer,
+            bind_group_layout,
+            bind_group,
+            escape_values_pipeline,
+            cumulative_pipeline,
+            render_pipeline,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, size: u64, label: &str) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_escape_values_buffer(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Buffer {
+        Self::create_buffer(
+            device,
+            width as u64 * height as u64 * 4,
+            "Histogram Escape Values Buffer",
+        )
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        escape_values_buffer: &wgpu::Buffer,
+        histogram_buffer: &wgpu::Buffer,
+        cumulative_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: escape_values_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: histogram_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: cumulative_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Histogram_bind_group"),
+        })
+    }
+
+    /// Reallocate the per pixel buffer for a new screen size
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.escape_values_buffer = Self::create_escape_values_buffer(device, width, height);
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.escape_values_buffer,
+            &self.histogram_buffer,
+            &self.cumulative_buffer,
+        );
+    }
+
+    /// Record the compute passes. `bind_groups` are the groups used by shader.wgsl, in the
+    /// same order as the layouts given to `new`.
+    pub fn compute(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        bind_groups: &[&wgpu::BindGroup],
+        width: u32,
+        height: u32,
+    ) {
+        encoder.clear_buffer(&self.histogram_buffer, 0, None);
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Histogram Compute Pass"),
+        });
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            compute_pass.set_bind_group(i as u32, bind_group, &[]);
+        }
+        compute_pass.set_bind_group(bind_groups.len() as u32, &self.bind_group, &[]);
+
+        compute_pass.set_pipeline(&self.escape_values_pipeline);
+        compute_pass.dispatch_workgroups(width.div_ceil(8), height.div_ceil(8), 1);
+        compute_pass.set_pipeline(&self.cumulative_pipeline);
+        compute_pass.dispatch_workgroups(1, 1, 1);
+    }
+
+    /// Bind group of the histogram buffers, to set after the shader.wgsl ones in the render pass
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
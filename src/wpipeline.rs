@@ -67,6 +67,7 @@ impl WPipeline {
         device: &wgpu::Device,
         bind_groups_layouts: &[&wgpu::BindGroupLayout],
         shader_code: Cow<'_, str>,
+        fragment_entry_point: &str,
         texture_format: wgpu::TextureFormat,
         label: &str,
     ) -> WPipeline {
@@ -92,7 +93,7 @@ impl WPipeline {
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
-                entry_point: "fs_main",
+                entry_point: fragment_entry_point,
                 targets: &[Some(wgpu::ColorTargetState {
                     format: texture_format,
                     blend: Some(wgpu::BlendState {
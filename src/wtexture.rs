@@ -0,0 +1,120 @@
+use crate::algcolor::RGB;
+
+/// Palette stored as a `width` x 1 RGBA texture, sampled with linear filtering so the shader
+/// interpolates between adjacent colors
+pub struct WTexture {
+    pub texture: wgpu::Texture,
+    pub width: u32,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl WTexture {
+    pub fn new_fragment_palette(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        palette: &[RGB],
+        label: &str,
+    ) -> Self {
+        let width = palette.len() as u32;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&format!("{} Texture", label)[..]),
+            size: wgpu::Extent3d {
+                width,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            // WebGL has no 1D textures, so the palette is a single row 2D texture
+            dimension: wgpu::TextureDimension::D2,
+            // Palette colors are sRGB like the surface, so the bytes written by the
+            // shader match the ones in the palette
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(&format!("{} Sampler", label)[..]),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some(&format!("{}_bind_group_layout", label)[..]),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some(&format!("{}_bind_group", label)[..]),
+        });
+
+        let wtexture = WTexture {
+            texture,
+            width,
+            bind_group_layout,
+            bind_group,
+        };
+        wtexture.write(queue, palette);
+        wtexture
+    }
+
+    /// Replace the palette colors. `palette` must have the same length as the one used to
+    /// create the texture.
+    pub fn write(&self, queue: &wgpu::Queue, palette: &[RGB]) {
+        let data: Vec<u8> = palette.iter().flat_map(|c| [c.r, c.g, c.b, 255]).collect();
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * self.width),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
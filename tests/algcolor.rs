@@ -0,0 +1,114 @@
+use wgpu_mandelbrot::algcolor::{
+    ColorParseError, ColorStop, Extend, Gradient, GradientError, Interpolation, RGB,
+};
+
+const BLACK: RGB = RGB { r: 0, g: 0, b: 0 };
+const WHITE: RGB = RGB {
+    r: 255,
+    g: 255,
+    b: 255,
+};
+
+fn stop(position: f64, color: RGB) -> ColorStop {
+    ColorStop { position, color }
+}
+
+#[test]
+fn gradient_rejects_invalid_stops() {
+    assert_eq!(Gradient::new(vec![]).unwrap_err(), GradientError::NoStops);
+    assert_eq!(
+        Gradient::new(vec![stop(0., BLACK), stop(1.5, WHITE)]).unwrap_err(),
+        GradientError::PositionOutOfRange(1.5)
+    );
+    assert_eq!(
+        Gradient::new(vec![stop(0.8, BLACK), stop(0.2, WHITE)]).unwrap_err(),
+        GradientError::UnsortedStops
+    );
+}
+
+#[test]
+fn gradient_interpolates_between_positioned_stops() {
+    let gradient = Gradient::new(vec![stop(0.25, BLACK), stop(0.75, WHITE)]).unwrap();
+    assert_eq!(gradient.sample(0.).r, 0);
+    assert_eq!(gradient.sample(0.5).r, 128);
+    assert_eq!(gradient.sample(1.).r, 255);
+    assert_eq!(gradient.palette(2).len(), 2);
+    assert!(gradient.palette(0).is_empty());
+}
+
+#[test]
+fn gradient_extend_modes() {
+    let gradient = Gradient::evenly_spaced(&[BLACK, WHITE]).unwrap();
+    assert_eq!(gradient.sample(1.25).r, 255);
+    let repeat = gradient.clone().with_extend(Extend::Repeat);
+    assert_eq!(repeat.sample(1.25).r, 64);
+    let mirror = gradient.with_extend(Extend::Mirror);
+    assert_eq!(mirror.sample(1.25).r, 191);
+}
+
+#[test]
+fn interpolation_color_spaces() {
+    let magenta = RGB::hex("E810DD");
+    let green = RGB::hex("33B242");
+    for interpolation in Interpolation::ALL {
+        let start = magenta.mix(green, 0., interpolation);
+        let end = magenta.mix(green, 1., interpolation);
+        assert!(start.r.abs_diff(magenta.r) <= 1 && start.b.abs_diff(magenta.b) <= 1);
+        assert!(end.g.abs_diff(green.g) <= 1 && end.b.abs_diff(green.b) <= 1);
+    }
+
+    // Linear light midpoints are brighter than the muddy sRGB average
+    let srgb = BLACK.mix(WHITE, 0.5, Interpolation::Srgb);
+    let linear = BLACK.mix(WHITE, 0.5, Interpolation::LinearRgb);
+    assert_eq!(srgb.r, 128);
+    assert_eq!(linear.r, 188);
+
+    // Hue follows the shortest arc: red to magenta goes through pink, not green
+    let red = RGB { r: 255, g: 0, b: 0 };
+    let magenta = RGB {
+        r: 255,
+        g: 0,
+        b: 255,
+    };
+    let mid = red.mix(magenta, 0.5, Interpolation::Hsv);
+    assert_eq!((mid.r, mid.g, mid.b), (255, 0, 128));
+}
+
+#[test]
+fn parse_hex_and_named_colors() {
+    let expected = RGB {
+        r: 0xE8,
+        g: 0x10,
+        b: 0xDD,
+    };
+    assert_eq!("E810DD".parse(), Ok(expected));
+    assert_eq!("#e810dd".parse(), Ok(expected));
+    assert_eq!("#E810DD80".parse(), Ok(expected));
+    assert_eq!(
+        RGB::try_from("#f0a"),
+        Ok(RGB {
+            r: 255,
+            g: 0,
+            b: 170
+        })
+    );
+    assert_eq!("RebeccaPurple".parse(), Ok(RGB::hex("663399")));
+    assert_eq!(expected.to_string(), "#E810DD");
+}
+
+#[test]
+fn parse_invalid_colors() {
+    assert_eq!("".parse::<RGB>(), Err(ColorParseError::Empty));
+    assert_eq!(
+        "#12345".parse::<RGB>(),
+        Err(ColorParseError::InvalidLength(5))
+    );
+    assert_eq!(
+        "#12é4".parse::<RGB>(),
+        Err(ColorParseError::InvalidHexDigit('é'))
+    );
+    assert_eq!(
+        "notacolor".parse::<RGB>(),
+        Err(ColorParseError::UnknownName("notacolor".to_string()))
+    );
+}
@@ -0,0 +1,24 @@
+use clap::Parser;
+use wgpu_mandelbrot::cli::Args;
+
+#[test]
+fn out_of_range_arguments_are_rejected() {
+    for arg in [
+        "--power=1",
+        "--power=0",
+        "--scale=0",
+        "--scale=-1",
+        "--scale=-1e400",
+        "--scale=1e999999999",
+        "--cycle-length=0",
+        "-x=1e999999999",
+        "-y=-1e-999999999",
+    ] {
+        assert!(
+            Args::try_parse_from(["multi_thread", arg]).is_err(),
+            "{}",
+            arg
+        );
+    }
+    assert_eq!(Args::parse_from(["multi_thread", "--power=2"]).power, 2);
+}
@@ -0,0 +1,167 @@
+use num::bigint::BigInt;
+use num::complex::Complex;
+use num::rational::BigRational;
+use num::traits::{Float, Num, One, Signed, ToPrimitive, Zero};
+use wgpu_mandelbrot::floatexp::{self, FloatExp};
+use wgpu_mandelbrot::precision::Fixed;
+
+/// Exact value of `x`
+fn exact(x: FloatExp) -> BigRational {
+    let (mantissa, exponent, sign) = x.mantissa().integer_decode();
+    let mantissa = BigRational::from_integer(BigInt::from(sign) * BigInt::from(mantissa));
+    let exponent = exponent as i64 + x.exponent();
+    let power = BigRational::from_integer(BigInt::one() << exponent.unsigned_abs() as usize);
+    if exponent >= 0 {
+        mantissa * power
+    } else {
+        mantissa / power
+    }
+}
+
+/// Error of `x` relative to the exact value `y`
+fn relative_error(x: FloatExp, y: &BigRational) -> f64 {
+    if y.is_zero() {
+        return if x.is_zero() { 0. } else { f64::INFINITY };
+    }
+    ((exact(x) - y) / y).abs().to_f64().unwrap()
+}
+
+/// Deterministic numbers with exponents far past the range of f64, both signs and zero
+fn samples() -> Vec<FloatExp> {
+    let mut state = 0x2545f4914f6cdd1d_u64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut samples = vec![FloatExp::zero(), FloatExp::one(), FloatExp::from(-0.75)];
+    for _ in 0..24 {
+        let mantissa = 1. + (next() >> 11) as f64 / (1u64 << 53) as f64;
+        let sign = if next() % 2 == 0 { 1. } else { -1. };
+        let exponent = (next() % 4000) as i64 - 2000;
+        samples.push(FloatExp::new(sign * mantissa, exponent));
+    }
+    samples
+}
+
+/// A few units in the last place of f64
+const TOLERANCE: f64 = 4. * f64::EPSILON;
+
+#[test]
+fn arithmetic_matches_exact_rationals() {
+    let samples = samples();
+    for &a in &samples {
+        for &b in &samples {
+            let (x, y) = (exact(a), exact(b));
+            // Sums of numbers of very different magnitudes keep the larger one
+            assert!(
+                relative_error(a + b, &(&x + &y)) <= TOLERANCE,
+                "{:?} + {:?}",
+                a,
+                b
+            );
+            assert!(
+                relative_error(a - b, &(&x - &y)) <= TOLERANCE,
+                "{:?} - {:?}",
+                a,
+                b
+            );
+            assert!(
+                relative_error(a * b, &(&x * &y)) <= TOLERANCE,
+                "{:?} * {:?}",
+                a,
+                b
+            );
+            if !b.is_zero() {
+                assert!(
+                    relative_error(a / b, &(&x / &y)) <= TOLERANCE,
+                    "{:?} / {:?}",
+                    a,
+                    b
+                );
+            }
+            assert_eq!(a < b, x < y, "{:?} < {:?}", a, b);
+        }
+        let x = exact(a);
+        assert_eq!(exact(-a), -&x);
+        assert_eq!(exact(a.abs()), x.abs());
+        assert!(relative_error(a.powi(3), &(&x * &x * &x)) <= TOLERANCE);
+        if !a.is_zero() {
+            assert!(relative_error(a.powi(-2), &(&x * &x).recip()) <= TOLERANCE);
+        }
+        let root = a.abs().sqrt();
+        assert!(relative_error(root * root, &x.abs()) <= TOLERANCE);
+    }
+}
+
+#[test]
+fn conversions_to_f64_and_fixed() {
+    for x in [1., -3.5, 1e-300, 4.9e-324, -2.2e-308, 1.7e308, 0.1] {
+        let y = FloatExp::from(x);
+        assert_eq!(y.to_f64(), x);
+        assert_eq!(exact(y), BigRational::from_float(x).unwrap());
+    }
+    assert_eq!(FloatExp::new(1., 2000).to_f64(), f64::INFINITY);
+    assert_eq!(FloatExp::new(-1., -2000).to_f64(), 0.);
+    assert_eq!(FloatExp::new(1., -1074).to_f64(), 4.9e-324);
+
+    // Fixed point holds the numbers of its range exactly
+    for x in samples()
+        .into_iter()
+        .filter(|x| x.exponent() > -4000 && x.exponent() < 30)
+    {
+        assert_eq!(Fixed::from_floatexp(x, 4100).to_floatexp(), x);
+    }
+
+    let z = Complex::new(FloatExp::new(1.5, -2000), FloatExp::new(-1., -2001));
+    assert_eq!(floatexp::complex_to_f64(z), Complex::new(0., -0.));
+    assert!(
+        relative_error(
+            floatexp::norm(z),
+            &exact(FloatExp::new(2.5f64.sqrt(), -2000))
+        ) <= TOLERANCE
+    );
+}
+
+#[test]
+fn decimal_strings_past_the_range_of_f64() {
+    let ten = BigRational::from_integer(BigInt::from(10));
+    let power = |e: i32| {
+        if e >= 0 {
+            num::pow(ten.clone(), e as usize)
+        } else {
+            num::pow(ten.clone(), (-e) as usize).recip()
+        }
+    };
+    for (s, mantissa, e) in [
+        ("1e400", 1, 400),
+        ("-25e-1000", -25, -1000),
+        ("3E+5000", 3, 5000),
+        ("7e-100000", 7, -100000),
+    ] {
+        let x: FloatExp = s.parse().unwrap();
+        let expected = BigRational::from_integer(BigInt::from(mantissa)) * power(e);
+        assert!(relative_error(x, &expected) <= 1e-14, "{}", s);
+        let printed: FloatExp = x.to_string().parse().unwrap();
+        assert!(relative_error(printed, &expected) <= 1e-14, "{}", x);
+    }
+    for s in ["35000.", "-0.25", "1e-20", "1.5e300", "0e999"] {
+        let x: FloatExp = s.parse().unwrap();
+        assert_eq!(x.to_f64(), s.parse::<f64>().unwrap());
+        assert_eq!(x.to_string(), x.to_f64().to_string());
+    }
+    for s in [
+        "",
+        "e5",
+        "1e",
+        "inf",
+        "nan",
+        "1.5.2e3",
+        "1e999999999",
+        "1e-9223372036854775808",
+    ] {
+        assert!(s.parse::<FloatExp>().is_err(), "{}", s);
+    }
+    assert!(FloatExp::from_str_radix("ff", 16).is_err());
+}
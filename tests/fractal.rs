@@ -0,0 +1,410 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use num::complex::Complex;
+use wgpu_mandelbrot::algcolor;
+use wgpu_mandelbrot::boundary;
+use wgpu_mandelbrot::coloring::{self, Coloring, Histogram, Interior};
+use wgpu_mandelbrot::fractal::{
+    self, Algorithm, Fractal, Julia, Mandelbrot, Multibrot, Orbit, RenderOptions, Viewport,
+};
+use wgpu_mandelbrot::subdivision;
+use wgpu_mandelbrot::supersampling::{Filter, Pattern, Supersampling};
+
+const OPTIONS: RenderOptions = RenderOptions {
+    max_iter: 200,
+    ..RenderOptions::DEFAULT
+};
+
+#[test]
+fn divergence_of_interior_and_exterior_points() {
+    assert_eq!(
+        fractal::mandelbrot_divergence(Complex::new(0., 0.), 100),
+        100
+    );
+    assert_eq!(
+        fractal::mandelbrot_divergence(Complex::new(-1., 0.), 100),
+        100
+    );
+    assert_eq!(fractal::mandelbrot_divergence(Complex::new(2., 2.), 100), 1);
+}
+
+#[test]
+fn smooth_count_stays_close_to_integer_count() {
+    let bailout = fractal::SMOOTH_BAILOUT;
+    let interior = fractal::smooth_escape_time(&Mandelbrot, Complex::new(-0.1, 0.1), 100, bailout);
+    assert_eq!(interior, 100.);
+
+    for re in [-0.75, 0.26, 0.3, 0.4] {
+        let c = Complex::new(re, 0.1);
+        let n = fractal::escape_time(&Mandelbrot, c, 100) as f64;
+        let mu = fractal::smooth_escape_time(&Mandelbrot, c, 100, bailout);
+        assert!(mu >= n - 1. && mu <= n + 4., "{} vs {}", mu, n);
+    }
+}
+
+#[test]
+fn viewport_maps_corners_to_scaled_extent() {
+    let viewport = Viewport::new(-0.5, 0.25, 2., 100, 100);
+    assert_eq!(viewport.pixel_to_complex(0, 0), Complex::new(-1.5, -0.75));
+    assert_eq!(viewport.pixel_to_complex(50, 50), Complex::new(-0.5, 0.25));
+
+    // The shorter side keeps the extent, the longer one grows with the aspect ratio
+    let wide = Viewport::new(-0.5, 0.25, 2., 200, 100);
+    assert_eq!(wide.pixel_to_complex(0, 0), Complex::new(-2.5, -0.75));
+    assert_eq!(wide.pixel_to_complex(100, 50), Complex::new(-0.5, 0.25));
+    let tall = Viewport::new(-0.5, 0.25, 2., 100, 300);
+    assert_eq!(tall.pixel_to_complex(0, 0), Complex::new(-1.5, -2.75));
+}
+
+#[test]
+fn parallel_and_single_thread_renders_match() {
+    let viewport = Viewport::new(-0.9170575, -0.277587, 35000., 40, 30);
+    let palette = algcolor::default_palette(200);
+    let pixels = fractal::render(&viewport, &Mandelbrot, &OPTIONS, &palette);
+    assert_eq!(pixels.len(), 40 * 30);
+    assert_eq!(
+        pixels,
+        fractal::render_single_thread(&viewport, &Mandelbrot, &OPTIONS, &palette)
+    );
+}
+
+#[test]
+fn multibrot_of_power_two_is_mandelbrot() {
+    let viewport = Viewport::new(-0.5, 0., 1., 32, 32);
+    let palette = algcolor::default_palette(200);
+    assert_eq!(
+        fractal::render(&viewport, &Multibrot { power: 2 }, &OPTIONS, &palette),
+        fractal::render(&viewport, &Mandelbrot, &OPTIONS, &palette)
+    );
+}
+
+#[test]
+fn histogram_spreads_counts_over_the_palette() {
+    let values = [1., 2., 2., 2.5, 3., 10.];
+    let histogram = Histogram::new(&values, 10);
+    assert_eq!(histogram.position(1.), 0.);
+    assert_eq!(histogram.position(2.), 0.2);
+    assert!((histogram.position(2.5) - 0.5).abs() < 1e-12);
+    assert_eq!(histogram.position(3.), 0.8);
+    assert_eq!(histogram.position(9.), 1.);
+    assert_eq!(histogram.position(10.), 1.);
+
+    let viewport = Viewport::new(-0.5, 0., 1., 16, 16);
+    let options = RenderOptions {
+        coloring: Coloring::Histogram,
+        ..OPTIONS
+    };
+    let pixels = fractal::render(
+        &viewport,
+        &Mandelbrot,
+        &options,
+        &algcolor::default_palette(200),
+    );
+    assert_eq!(pixels.len(), 16 * 16);
+}
+
+#[test]
+fn cyclic_position_mirrors_without_seams() {
+    assert_eq!(coloring::cyclic_position(1., 4., 0.), 0.);
+    assert_eq!(coloring::cyclic_position(3., 4., 0.), 0.5);
+    assert_eq!(coloring::cyclic_position(5., 4., 0.), 1.);
+    assert_eq!(coloring::cyclic_position(7., 4., 0.), 0.5);
+    assert_eq!(coloring::cyclic_position(9., 4., 0.), 0.);
+    assert_eq!(coloring::cyclic_position(1., 4., 0.25), 0.25);
+    assert_eq!(coloring::cyclic_position(1., 4., -0.25), 0.25);
+}
+
+#[test]
+fn supersampling_patterns_and_filters() {
+    let rotated = Supersampling {
+        samples: 3,
+        pattern: Pattern::Rotated,
+        filter: Filter::Box,
+        adaptive_threshold: None,
+    };
+    let offsets: Vec<(f64, f64)> = (0..9).map(|i| rotated.offset(4, 7, i)).collect();
+    // Every row and every column of the pixel holds a single sample
+    for axis in [|o: &(f64, f64)| o.0, |o: &(f64, f64)| o.1] {
+        let mut cells: Vec<usize> = offsets.iter().map(|o| (axis(o) * 9.) as usize).collect();
+        cells.sort();
+        assert_eq!(cells, (0..9).collect::<Vec<_>>());
+    }
+
+    let jitter = Supersampling {
+        pattern: Pattern::Jitter,
+        ..rotated
+    };
+    for i in 0..9 {
+        let (x, y) = jitter.offset(4, 7, i);
+        assert_eq!((x * 3.) as u32, i % 3);
+        assert_eq!((y * 3.) as u32, i / 3);
+        assert_eq!(jitter.offset(4, 7, i), (x, y));
+    }
+
+    // Flat colors stay flat whatever the filter
+    let colors = vec![[0.25, 0.5, 1.]; 5 * 4 * 9];
+    for filter in [Filter::Box, Filter::Gaussian] {
+        let sampling = Supersampling { filter, ..rotated };
+        for pixel in sampling.resolve(&colors, 5, 4) {
+            for (a, b) in pixel.iter().zip([0.25, 0.5, 1.]) {
+                assert!((a - b).abs() < 1e-12);
+            }
+        }
+    }
+
+    let viewport = Viewport::new(-0.5, 0., 1., 20, 10);
+    let options = RenderOptions {
+        supersampling: Supersampling {
+            filter: Filter::Gaussian,
+            ..jitter
+        },
+        ..OPTIONS
+    };
+    let palette = algcolor::default_palette(200);
+    let pixels = fractal::render(&viewport, &Mandelbrot, &options, &palette);
+    assert_eq!(pixels.len(), 20 * 10);
+    assert_eq!(
+        pixels,
+        fractal::render_single_thread(&viewport, &Mandelbrot, &options, &palette)
+    );
+}
+
+#[test]
+fn adaptive_supersampling_refines_edges_only() {
+    let viewport = Viewport::new(-0.5, 0., 1., 40, 30);
+    let palette = algcolor::default_palette(200);
+    let supersampling = Supersampling {
+        samples: 3,
+        pattern: Pattern::Rotated,
+        filter: Filter::Box,
+        adaptive_threshold: None,
+    };
+    let full = RenderOptions {
+        supersampling,
+        ..OPTIONS
+    };
+    let adaptive = RenderOptions {
+        supersampling: Supersampling {
+            adaptive_threshold: Some(0.05),
+            ..supersampling
+        },
+        ..OPTIONS
+    };
+
+    let (single, refined) = fractal::render_colors(&viewport, &Mandelbrot, &OPTIONS, &palette);
+    assert_eq!(refined, 0);
+    let (full, refined) = fractal::render_colors(&viewport, &Mandelbrot, &full, &palette);
+    assert_eq!(refined, 40 * 30);
+    let (colors, refined) = fractal::render_colors(&viewport, &Mandelbrot, &adaptive, &palette);
+    assert!(refined > 0 && refined < 40 * 30, "{}", refined);
+
+    // Pixels are either left as they were or supersampled like in the full render
+    let mut changed = 0;
+    for ((c, s), f) in colors.iter().zip(&single).zip(&full) {
+        if c != s {
+            changed += 1;
+            assert!(c.iter().zip(f).all(|(a, b)| (a - b).abs() < 1e-9));
+        }
+    }
+    assert!(changed > 0 && changed <= refined);
+    assert_eq!(
+        (colors, refined),
+        fractal::render_colors_single_thread(&viewport, &Mandelbrot, &adaptive, &palette)
+    );
+}
+
+#[test]
+fn interior_check_keeps_renders_identical() {
+    let period = |re, im| fractal::cardioid_or_bulb_period(Complex::new(re, im));
+    assert_eq!(period(0., 0.), Some(1));
+    assert_eq!(period(-0.7, 0.2), Some(1));
+    assert_eq!(period(-1., 0.2), Some(2));
+    assert_eq!(period(0.3, 0.), None);
+    assert_eq!(period(-1.3, 0.), None);
+    // Inside the set but in a smaller bulb
+    assert_eq!(period(-0.12, 0.75), None);
+
+    let viewport = Viewport::new(-0.6, 0., 0.9, 48, 32);
+    let palette = algcolor::default_palette(200);
+    let unchecked = RenderOptions {
+        interior_check: false,
+        ..OPTIONS
+    };
+    assert_eq!(
+        fractal::render(&viewport, &Mandelbrot, &OPTIONS, &palette),
+        fractal::render(&viewport, &Mandelbrot, &unchecked, &palette)
+    );
+}
+
+#[test]
+fn periodicity_check_finds_cycles() {
+    let orbit = |re, im, tolerance| {
+        fractal::iterate(&Mandelbrot, Complex::new(re, im), 10000, 2., tolerance)
+    };
+    let period = |re, im| match orbit(re, im, Some(1e-10)) {
+        Orbit::Bounded { period } => period,
+        escaped => panic!("{:?}", escaped),
+    };
+    assert_eq!(period(0., 0.), Some(1));
+    assert_eq!(period(-0.2, 0.1), Some(1));
+    assert_eq!(period(-1., 0.), Some(2));
+    assert_eq!(period(-0.12, 0.75), Some(3));
+    assert_eq!(period(-1.3, 0.), Some(4));
+    // Near the border of the period-4 bulb the orbits converge slowly and can first come back
+    // close to themselves after a multiple of the period
+    for (re, im) in [(-1.33417, -0.00417), (-1.3492, -0.01875)] {
+        for tolerance in [1e-4, 1e-6, 1e-8, 1e-10, 1e-12] {
+            match orbit(re, im, Some(tolerance)) {
+                Orbit::Bounded { period } => assert_eq!(period, Some(4), "{} {}", re, tolerance),
+                escaped => panic!("{:?}", escaped),
+            }
+        }
+    }
+    assert_eq!(orbit(0.3, 0., Some(1e-10)), orbit(0.3, 0., None));
+    assert_eq!(orbit(-0.12, 0.75, None), Orbit::Bounded { period: None });
+
+    let viewport = Viewport::new(-0.6, 0., 0.9, 48, 32);
+    let palette = algcolor::default_palette(200);
+    let unchecked = RenderOptions {
+        periodicity_check: false,
+        ..OPTIONS
+    };
+    let flat = fractal::render(&viewport, &Mandelbrot, &OPTIONS, &palette);
+    assert_eq!(
+        flat,
+        fractal::render(&viewport, &Mandelbrot, &unchecked, &palette)
+    );
+
+    // Shading by period only changes the points of the set
+    let shaded = RenderOptions {
+        interior: Interior::Period,
+        ..OPTIONS
+    };
+    let values = fractal::escape_values(&viewport, &Mandelbrot, &shaded);
+    let pixels = fractal::render(&viewport, &Mandelbrot, &shaded, &palette);
+    for ((a, b), &n) in pixels.iter().zip(&flat).zip(&values) {
+        if fractal::interior_period(n, OPTIONS.max_iter).is_none() {
+            assert_eq!(a, b);
+        }
+    }
+    assert_ne!(pixels, flat);
+}
+
+/// Compute reference views with `algorithm`, in smooth and discrete modes with flat and
+/// period shading, and compare the escape values with the brute-force ones
+fn assert_matches_brute_force(algorithm: Algorithm) {
+    let julia = Julia {
+        k: Complex::new(-0.4, 0.3),
+    };
+    // Default constant of the command line
+    let default_julia = Julia {
+        k: Complex::new(-0.8, 0.156),
+    };
+    // The next two contain the whole set, surrounded by escape bands
+    let views: [(&dyn Fractal, Viewport); 10] = [
+        (&Mandelbrot, Viewport::new(-0.5, 0., 1., 96, 64)),
+        (&Mandelbrot, Viewport::new(-0.75, 0.1, 20., 64, 64)),
+        (&Mandelbrot, Viewport::new(0.28, 0.008, 60., 80, 48)),
+        (&Mandelbrot, Viewport::new(-1.768, 0.001, 90., 64, 64)),
+        (&julia, Viewport::new(0.2, 0.3, 3., 96, 64)),
+        (&Mandelbrot, Viewport::new(0., 0., 0.9, 90, 90)),
+        (&default_julia, Viewport::new(0., 0., 1., 90, 90)),
+        // Components of several periods next to each other
+        (
+            &Mandelbrot,
+            Viewport::new(-1.3465996283800334, -0.030768495287984532, 24.517, 128, 96),
+        ),
+        (&Mandelbrot, Viewport::new(-1.755, 0., 12., 120, 40)),
+        // The whole set inside the first strip of the boundary tracing
+        (&Mandelbrot, Viewport::new(0., 0., 0.3, 160, 40)),
+    ];
+    for smooth in [true, false] {
+        for interior in [Interior::Flat, Interior::Period] {
+            let brute = RenderOptions {
+                max_iter: 300,
+                smooth,
+                interior,
+                ..OPTIONS
+            };
+            let fast = RenderOptions { algorithm, ..brute };
+            for (fractal, viewport) in &views {
+                let values = fractal::escape_values(viewport, *fractal, &brute);
+                assert_eq!(
+                    values,
+                    fractal::escape_values(viewport, *fractal, &fast),
+                    "{:?} {:?}",
+                    viewport,
+                    interior
+                );
+                assert_eq!(
+                    values,
+                    fractal::escape_values_single_thread(viewport, *fractal, &fast)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn subdivision_matches_brute_force() {
+    // Uniform regions are filled from their border
+    let calls = AtomicU32::new(0);
+    let value = |x: u32, _| {
+        calls.fetch_add(1, Ordering::Relaxed);
+        if x < 20 {
+            x as f64
+        } else {
+            7.
+        }
+    };
+    let values = subdivision::subdivide((64, 48), true, value, |_| true);
+    for (i, &v) in values.iter().enumerate() {
+        assert_eq!(v, if i % 64 < 20 { (i % 64) as f64 } else { 7. });
+    }
+    assert!(calls.swap(0, Ordering::Relaxed) < 64 * 48 / 2);
+    assert_eq!(
+        subdivision::subdivide((64, 48), false, value, |v| v != 7.),
+        values
+    );
+    assert_eq!(calls.into_inner(), 64 * 48);
+
+    assert_matches_brute_force(Algorithm::Subdivision);
+}
+
+#[test]
+fn boundary_tracing_matches_brute_force() {
+    // Only the pixels along the edge of the disk are computed
+    let calls = AtomicU32::new(0);
+    let value = |x: u32, y: u32| {
+        calls.fetch_add(1, Ordering::Relaxed);
+        let (dx, dy) = (x as f64 - 40., y as f64 - 30.);
+        if dx * dx + dy * dy < 400. {
+            1.
+        } else {
+            2.
+        }
+    };
+    let values = boundary::trace((80, 60), true, value, |_| true);
+    assert!(calls.load(Ordering::Relaxed) < 80 * 60 / 2);
+    for (i, &v) in values.iter().enumerate() {
+        assert_eq!(v, value(i as u32 % 80, i as u32 / 80));
+    }
+    assert_eq!(boundary::trace((80, 60), false, value, |_| false), values);
+
+    // A disk inside a single strip is an island of the region around it, which is computed
+    // pixel by pixel to reach it
+    let island = |x: u32, y: u32| {
+        let (dx, dy) = (x as f64 - 40., y as f64 - 16.);
+        if dx * dx + dy * dy < 100. {
+            1.
+        } else {
+            2.
+        }
+    };
+    let values = boundary::trace((80, 60), true, island, |v| v == 1.);
+    for (i, &v) in values.iter().enumerate() {
+        assert_eq!(v, island(i as u32 % 80, i as u32 / 80), "{}", i);
+    }
+
+    assert_matches_brute_force(Algorithm::BoundaryTrace);
+}
@@ -0,0 +1,159 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+use clap::Parser;
+use wgpu_mandelbrot::cli::Args;
+
+use wgpu_mandelbrot::algcolor;
+use wgpu_mandelbrot::coloring::Coloring;
+use wgpu_mandelbrot::floatexp::FloatExp;
+use wgpu_mandelbrot::fractal::{self, Algorithm, Mandelbrot, RenderOptions, Viewport};
+use wgpu_mandelbrot::output::{self, BitDepth, Format, OutputError};
+
+const OPTIONS: RenderOptions = RenderOptions {
+    max_iter: 100,
+    ..RenderOptions::DEFAULT
+};
+
+#[test]
+fn format_from_extension() {
+    let format = |path: &str, depth| Format::from_path(Path::new(path), depth);
+    assert_eq!(format("a.PNG", BitDepth::Eight).unwrap(), Format::Png);
+    assert_eq!(format("a.png", BitDepth::Sixteen).unwrap(), Format::Png16);
+    assert_eq!(format("a.jpeg", BitDepth::Eight).unwrap(), Format::Jpeg);
+    assert_eq!(format("a.tif", BitDepth::Eight).unwrap(), Format::Tiff);
+    assert_eq!(format("dir/a.ppm", BitDepth::Eight).unwrap(), Format::Ppm);
+    assert!(matches!(
+        format("a.gif", BitDepth::Eight),
+        Err(OutputError::UnknownExtension(_))
+    ));
+    assert!(matches!(
+        format("a", BitDepth::Eight),
+        Err(OutputError::UnknownExtension(_))
+    ));
+    assert!(matches!(
+        format("a.jpg", BitDepth::Sixteen),
+        Err(OutputError::UnsupportedDepth(_))
+    ));
+}
+
+#[test]
+fn save_every_format() {
+    let viewport = Viewport::new(-0.5, 0., 1., 24, 16);
+    let values = fractal::escape_values(&viewport, &Mandelbrot, &OPTIONS);
+    let palette = algcolor::default_palette(OPTIONS.max_iter);
+    let colors = fractal::resolve_colors(&viewport, &values, &OPTIONS, &palette);
+    let dir = std::env::temp_dir().join(format!("wgpu_mandelbrot_output_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    for (name, depth) in [
+        ("a.png", BitDepth::Eight),
+        ("a16.png", BitDepth::Sixteen),
+        ("a.jpg", BitDepth::Eight),
+        ("a.tiff", BitDepth::Eight),
+        ("a.webp", BitDepth::Eight),
+        ("a.bmp", BitDepth::Eight),
+        ("a.ppm", BitDepth::Eight),
+    ] {
+        let path = dir.join(name);
+        let format = Format::from_path(&path, depth).unwrap();
+        output::save(&path, format, (24, 16), &colors, &[]).unwrap();
+        let image = image::open(&path).unwrap();
+        assert_eq!((image.width(), image.height()), (24, 16), "{}", name);
+        if depth == BitDepth::Sixteen {
+            assert_eq!(image.color(), image::ColorType::Rgb16);
+        }
+    }
+    // Lossless formats keep the rendered colors
+    let pixels = fractal::render(&viewport, &Mandelbrot, &OPTIONS, &palette);
+    let ppm = image::open(dir.join("a.ppm")).unwrap().to_rgb8();
+    assert_eq!(ppm.pixels().map(|p| p.0).collect::<Vec<_>>(), pixels);
+    assert!(std::fs::read(dir.join("a.ppm")).unwrap().starts_with(b"P6"));
+
+    let missing_dir = dir.join("missing").join("a.png");
+    let error = output::save(&missing_dir, Format::Png, (24, 16), &colors, &[]);
+    assert!(matches!(error, Err(OutputError::Write { .. })));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn render_parameters_round_trip_through_png_metadata() {
+    let args = Args::parse_from([
+        "multi_thread",
+        "--fractal=julia",
+        "-x=-0.743643887037151",
+        "-y=0.131825904205330",
+        "--scale=123456.789",
+        "--width=30",
+        "--height=20",
+        "--max-iter=321",
+        "--discrete",
+        "--coloring=cyclic",
+        "--palette=#000,orange,white",
+        "--samples=2",
+        "--filter=gaussian",
+        "--adaptive=0.1",
+        "--interior=period",
+        "--no-interior-check",
+        "--no-periodicity-check",
+        "--algorithm=subdivision",
+        "--no-series-approximation",
+        "--bit-depth=16",
+    ]);
+    let viewport = args.viewport();
+    let options = args.render_options();
+    let values = fractal::escape_values(&viewport, args.fractal().as_ref(), &options);
+    let colors = fractal::resolve_colors(&viewport, &values, &options, &args.palette());
+    let path =
+        std::env::temp_dir().join(format!("wgpu_mandelbrot_meta_{}.png", std::process::id()));
+    let format = args.output_format().unwrap();
+    output::save(&path, format, (30, 20), &colors, &args.metadata()).unwrap();
+
+    let metadata = output::read_metadata(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let restored = Args::from_metadata(&metadata, [OsString::from("multi_thread")]).unwrap();
+    assert_eq!(restored.metadata(), args.metadata());
+    assert_eq!(restored.x_coord, "-0.743643887037151".parse().unwrap());
+    assert_eq!(restored.palette(), args.palette());
+    assert!(restored.no_interior_check && restored.no_periodicity_check);
+    assert!(restored.no_series_approximation);
+    assert_eq!(restored.algorithm, Algorithm::Subdivision);
+    assert_eq!(restored.bit_depth, BitDepth::Sixteen);
+
+    // Options on the command line replace the stored ones
+    let tweaked = Args::from_metadata(
+        &metadata,
+        ["multi_thread", "--scale", "2", "--coloring=linear"].map(OsString::from),
+    )
+    .unwrap();
+    assert_eq!(tweaked.scale, FloatExp::from(2.));
+    assert_eq!(tweaked.coloring, Coloring::Linear);
+    assert_eq!(tweaked.max_iter, 321);
+    assert!(tweaked.discrete);
+
+    // A square size replaces the stored width and height
+    let square =
+        Args::from_metadata(&metadata, ["multi_thread", "-i", "40"].map(OsString::from)).unwrap();
+    assert_eq!(square.viewport().width, 40);
+    assert_eq!(square.viewport().height, 40);
+
+    // Stored flags are undone by their opposites
+    let undone = Args::from_metadata(
+        &metadata,
+        [
+            "multi_thread",
+            "--smooth",
+            "--no-adaptive",
+            "--interior-check",
+            "--periodicity-check",
+            "--series-approximation",
+        ]
+        .map(OsString::from),
+    )
+    .unwrap();
+    let options = undone.render_options();
+    assert!(options.smooth && options.supersampling.adaptive_threshold.is_none());
+    assert!(options.interior_check && options.periodicity_check);
+    assert!(options.series_approximation);
+    assert!(!undone.metadata().iter().any(|(key, _)| key == "adaptive"));
+}
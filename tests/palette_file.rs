@@ -0,0 +1,34 @@
+use wgpu_mandelbrot::algcolor::RGB;
+use wgpu_mandelbrot::palette_file;
+
+#[test]
+fn palette_from_color_list() {
+    let gradient = palette_file::parse_palette("#000,white,FF0000").unwrap();
+    let stops = gradient.stops();
+    assert_eq!(stops.len(), 3);
+    assert_eq!(stops[1].position, 0.5);
+    assert_eq!(stops[2].color, RGB { r: 255, g: 0, b: 0 });
+    assert!(palette_file::parse_palette("#000,nocolor").is_err());
+}
+
+#[test]
+fn palette_from_files() {
+    let ggr = "GIMP Gradient\nName: Test\n2\n\
+               0.0 0.25 0.5 0 0 0 1 1 1 1 1 0 0\n\
+               0.5 0.75 1.0 1 0 0 1 0 0 1 1 0 0\n";
+    let gradient = palette_file::parse_ggr(ggr).unwrap();
+    assert_eq!(gradient.stops().len(), 6);
+    assert_eq!(gradient.sample(0.25), RGB::hex("808080"));
+    assert_eq!(gradient.sample(1.), RGB::hex("0000FF"));
+
+    let map = "0 0 0 black\n255 255 255\n\n";
+    assert_eq!(palette_file::parse_map(map).unwrap().stops().len(), 2);
+    assert!(palette_file::parse_map("0 0\n").is_err());
+
+    let json = r##"{"stops": [{"position": 0.0, "color": "#E810DD"}, {"position": 1.0, "color": "navy"}]}"##;
+    let gradient = palette_file::parse_json(json).unwrap();
+    assert_eq!(gradient.sample(1.), RGB::hex("000080"));
+
+    let toml = "[[stops]]\nposition = 0.0\ncolor = \"red\"\n\n[[stops]]\nposition = 2.0\ncolor = \"blue\"\n";
+    assert!(palette_file::parse_toml(toml).is_err());
+}
@@ -0,0 +1,175 @@
+use num::complex::Complex;
+use wgpu_mandelbrot::fractal::{
+    self, Algorithm, BurningShip, Fractal, Julia, Mandelbrot, Multibrot, RenderOptions, Tricorn,
+    Viewport,
+};
+use wgpu_mandelbrot::perturbation::Reference;
+
+const OPTIONS: RenderOptions = RenderOptions {
+    smooth: false,
+    interior_check: false,
+    periodicity_check: false,
+    algorithm: Algorithm::Perturbation,
+    ..RenderOptions::DEFAULT
+};
+
+const WITHOUT_SERIES: RenderOptions = RenderOptions {
+    series_approximation: false,
+    ..OPTIONS
+};
+
+const BRUTE_FORCE: RenderOptions = RenderOptions {
+    algorithm: Algorithm::BruteForce,
+    ..OPTIONS
+};
+
+/// Pixels of `a` and `b` with different escape values
+fn differences(a: &[f64], b: &[f64]) -> usize {
+    a.iter().zip(b).filter(|(x, y)| x != y).count()
+}
+
+#[test]
+fn perturbation_matches_brute_force() {
+    let julia = Julia {
+        k: Complex::new(-0.8, 0.156),
+    };
+    let views: [(&dyn Fractal, Viewport); 5] = [
+        (&Mandelbrot, Viewport::new(-0.5, 0., 1., 48, 32)),
+        (
+            &Mandelbrot,
+            Viewport::new(-0.9170575, -0.277587, 35000., 40, 40),
+        ),
+        (&Multibrot { power: 3 }, Viewport::new(0., 0., 1., 40, 40)),
+        (&Tricorn, Viewport::new(-0.3, 0., 1., 40, 40)),
+        (&julia, Viewport::new(0., 0., 1.2, 48, 32)),
+    ];
+    for (fractal, viewport) in views {
+        let exact = fractal::escape_values(&viewport, fractal, &BRUTE_FORCE);
+        let perturbed = fractal::escape_values(&viewport, fractal, &OPTIONS);
+        // Orbits close to the boundary can take another path after a rounding difference
+        assert!(
+            differences(&exact, &perturbed) * 100 <= exact.len(),
+            "{:?}",
+            viewport
+        );
+    }
+
+    // Fractals without a perturbation formula fall back to brute force
+    let viewport = Viewport::new(-0.5, -0.5, 1., 32, 32);
+    assert_eq!(
+        fractal::escape_values(&viewport, &BurningShip, &OPTIONS),
+        fractal::escape_values(&viewport, &BurningShip, &BRUTE_FORCE)
+    );
+}
+
+#[test]
+fn perturbation_matches_fixed_point_in_deep_zooms() {
+    // Around the Misiurewicz point i, whose reference orbit never escapes
+    let center = |s: &str| s.parse().unwrap();
+    let viewport = Viewport::with_center(&center("0"), &center("1"), 1e18.into(), 24, 24);
+    assert!(viewport.precision_bits().is_some());
+    assert_eq!(
+        Reference::new(&Mandelbrot, &viewport, 500, 2.).iterations(),
+        500
+    );
+    assert_eq!(
+        fractal::escape_values(&viewport, &Mandelbrot, &OPTIONS),
+        fractal::escape_values(&viewport, &Mandelbrot, &BRUTE_FORCE)
+    );
+
+    // The reference escapes before most pixels, which are rebased at the end of its orbit
+    let viewport = Viewport::with_center(
+        &center("0.000000000000000001"),
+        &center("1.000000000000000002"),
+        1e18.into(),
+        24,
+        24,
+    );
+    assert!(Reference::new(&Mandelbrot, &viewport, 500, 2.).iterations() < 60);
+    let exact = fractal::escape_values(&viewport, &Mandelbrot, &BRUTE_FORCE);
+    let perturbed = fractal::escape_values(&viewport, &Mandelbrot, &OPTIONS);
+    assert!(perturbed.iter().all(|&v| v > 40. && v < 500.));
+    assert!(differences(&exact, &perturbed) * 100 <= exact.len());
+}
+
+#[test]
+fn series_approximation_matches_full_perturbation() {
+    // Next to a minibrot of period 32 the reference stays bounded for a long time
+    let center = |s: &str| s.parse().unwrap();
+    let viewport = Viewport::with_center(
+        &center("-1.74972192974233857170203"),
+        &center("-0.00002901664775360131"),
+        1e17.into(),
+        24,
+        24,
+    );
+    let options = RenderOptions {
+        max_iter: 2000,
+        ..OPTIONS
+    };
+    let reference =
+        Reference::new(&Mandelbrot, &viewport, 2000, 2.).with_series(&Mandelbrot, &viewport);
+    assert!(reference.skipped() > 500, "{}", reference.skipped());
+    assert!(reference.skipped() < reference.iterations());
+    assert!(reference.series_error() <= 1e-9);
+    let without_series = RenderOptions {
+        series_approximation: false,
+        ..options
+    };
+    assert_eq!(
+        fractal::escape_values(&viewport, &Mandelbrot, &options),
+        fractal::escape_values(&viewport, &Mandelbrot, &without_series)
+    );
+
+    let julia = Julia {
+        k: Complex::new(-0.4, 0.6),
+    };
+    let views: [(&dyn Fractal, Viewport); 3] = [
+        (&Mandelbrot, Viewport::new(-0.75, 0.1, 1e4, 32, 32)),
+        (
+            &Multibrot { power: 3 },
+            Viewport::new(0.4, 0.6, 1e4, 32, 32),
+        ),
+        (&julia, Viewport::new(0.2, 0.3, 1e4, 32, 32)),
+    ];
+    for (fractal, viewport) in views {
+        let reference = Reference::new(fractal, &viewport, 500, 2.).with_series(fractal, &viewport);
+        assert!(reference.skipped() > 0, "{:?}", viewport);
+        let approximated = fractal::escape_values(&viewport, fractal, &OPTIONS);
+        let full = fractal::escape_values(&viewport, fractal, &WITHOUT_SERIES);
+        assert!(
+            differences(&approximated, &full) * 100 <= full.len(),
+            "{:?}",
+            viewport
+        );
+    }
+
+    // Tricorn is not analytic, its orbits start from the first iteration
+    let viewport = Viewport::new(-0.3, 0., 1e4, 32, 32);
+    let reference = Reference::new(&Tricorn, &viewport, 500, 2.).with_series(&Tricorn, &viewport);
+    assert_eq!(reference.skipped(), 0);
+}
+
+#[test]
+fn perturbation_iterates_in_floatexp_past_the_range_of_f64() {
+    // Around i again, with pixels too small for the f64 exponent
+    let center = |s: &str| s.parse().unwrap();
+    let scale = "1e320".parse().unwrap();
+    let viewport = Viewport::with_center(&center("0"), &center("1"), scale, 12, 12);
+    assert!(viewport.needs_floatexp());
+    let options = RenderOptions {
+        max_iter: 1500,
+        ..OPTIONS
+    };
+    let brute_force = RenderOptions {
+        max_iter: 1500,
+        ..BRUTE_FORCE
+    };
+    let perturbed = fractal::escape_values(&viewport, &Mandelbrot, &options);
+    assert!(perturbed.iter().any(|&v| v != perturbed[0]));
+    assert!(perturbed.iter().all(|&v| v < 1500.));
+    assert_eq!(
+        perturbed,
+        fractal::escape_values(&viewport, &Mandelbrot, &brute_force)
+    );
+}
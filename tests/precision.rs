@@ -0,0 +1,91 @@
+use wgpu_mandelbrot::fractal::{self, Mandelbrot, RenderOptions, Viewport};
+use wgpu_mandelbrot::precision::{Decimal, Fixed};
+
+#[test]
+fn decimals_parse_and_print_exactly() {
+    let decimal = |s: &str| s.parse::<Decimal>().unwrap();
+    for s in [
+        "-0.9170575",
+        "0.131825904205311970493132056385139",
+        "-1.0000000000000000000000000000001",
+        "12",
+        "0",
+    ] {
+        assert_eq!(decimal(s).to_string(), s);
+    }
+    assert_eq!(decimal("+.5").to_string(), "0.5");
+    assert_eq!(decimal("-1.5e-3").to_string(), "-0.0015");
+    assert_eq!(decimal("25E2").to_string(), "2500");
+    assert_eq!(decimal("1e-100000").to_string().len(), 100_002);
+    for s in [
+        "",
+        "-",
+        ".",
+        "1.2.3",
+        "0x10",
+        "1e",
+        "--1",
+        "1e999999999",
+        "1e-100001",
+        "1e-2147483648",
+    ] {
+        assert!(s.parse::<Decimal>().is_err(), "{:?}", s);
+    }
+
+    assert_eq!(decimal("-0.9170575").to_f64(), -0.9170575);
+    assert_eq!(Decimal::from(-0.9170575), decimal("-0.9170575"));
+    assert_eq!(Decimal::from(1e-30).to_f64(), 1e-30);
+}
+
+#[test]
+fn fixed_point_arithmetic() {
+    let bits = 256;
+    let third = "0.333333333333333333333333333333333333333333333333333333333333333"
+        .parse::<Decimal>()
+        .unwrap()
+        .to_fixed(bits);
+    let three = Fixed::from_f64(3., bits);
+    // 63 digits of 1/3 fall 1e-63 short
+    let one = &third * &three;
+    assert_eq!(one.to_f64(), 1.);
+    let missing = (&Fixed::from_f64(1., bits) - &one).to_f64();
+    assert!(missing > 0.99e-63 && missing < 1.01e-63, "{}", missing);
+    assert_eq!((&three - &three), Fixed::zero(bits));
+    assert_eq!((&three + &(-&three)).to_f64(), 0.);
+    assert_eq!(Fixed::from_f64(-2.75, bits).to_f64(), -2.75);
+    assert_eq!(Fixed::from_f64(-2.75, bits).abs().to_f64(), 2.75);
+
+    // Far past the precision of f64 around 1
+    let tiny = Fixed::from_f64(1e-40, bits);
+    let sum = &Fixed::from_f64(1., bits) + &tiny;
+    assert_eq!(sum.to_f64(), 1.);
+    assert_eq!((&sum - &Fixed::from_f64(1., bits)).to_f64(), tiny.to_f64());
+}
+
+#[test]
+fn deep_zoom_switches_to_fixed_point() {
+    let options = RenderOptions {
+        max_iter: 100,
+        smooth: false,
+        ..Default::default()
+    };
+    assert_eq!(Viewport::new(-0.5, 0., 1e9, 16, 16).precision_bits(), None);
+
+    // Pixels of about 2^-64 around the tip of the antenna, where f64 rounds every pixel of the
+    // real axis to -2. The real axis, through the middle row, is in the set from -2 on and
+    // escapes before it.
+    let viewport = Viewport::new(-2., 0., 2f64.powi(62), 15, 15);
+    assert!(viewport.precision_bits().is_some());
+    let values = fractal::escape_values(&viewport, &Mandelbrot, &options);
+    let axis = &values[7 * 15..8 * 15];
+    for (x, &value) in axis.iter().enumerate() {
+        assert_eq!(value >= 100., x >= 7, "{:?}", axis);
+    }
+    // Symmetric around the real axis
+    for y in 1..8 {
+        assert_eq!(
+            values[(7 - y) * 15..(8 - y) * 15],
+            values[(7 + y) * 15..(8 + y) * 15]
+        );
+    }
+}